@@ -0,0 +1,70 @@
+use std::{fmt::Write, fs, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lands_of_mana::game::load_map::LoadedMap;
+
+// Grass land tile in base tileset, tile ids in layers start from 1
+const GRASS_LAND: u32 = 21 + 1;
+
+/// Write a map of `size`x`size` grass tiles split into square provinces of
+/// `province_size` tiles, and return its path
+fn generate_map(size: u32, province_size: u32) -> PathBuf {
+    let empty_layer = vec!["0"; (size * size) as usize].join(",");
+    let base_layer = vec![GRASS_LAND.to_string(); (size * size) as usize].join(",");
+    let tile_layer = |id: u32, name: &str, data: &str| {
+        format!(
+            r#"<layer id="{id}" name="{name}" width="{size}" height="{size}"><data encoding="csv">{data}</data></layer>"#
+        )
+    };
+
+    let mut provinces = String::new();
+    let provinces_per_side = size / province_size;
+    let pixels = (province_size * 16) as f32;
+    for province_y in 0..provinces_per_side {
+        for province_x in 0..provinces_per_side {
+            write!(
+                provinces,
+                r#"<object id="{}" x="{}" y="{}"><polygon points="0,0 {pixels},0 {pixels},{pixels} 0,{pixels}"/></object>"#,
+                province_y * provinces_per_side + province_x + 1,
+                province_x as f32 * pixels,
+                province_y as f32 * pixels,
+            )
+            .unwrap();
+        }
+    }
+
+    let map = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.8" orientation="orthogonal" renderorder="right-down" width="{size}" height="{size}" tilewidth="16" tileheight="16" infinite="0">
+<tileset firstgid="1" name="base" tilewidth="16" tileheight="16" tilecount="32" columns="8"><image source="terrain_base.png" width="128" height="64"/></tileset>
+{}
+{}
+{}
+{}
+<objectgroup id="5" name="Provinces">{provinces}</objectgroup>
+<objectgroup id="6" name="Cities"></objectgroup>
+</map>"#,
+        tile_layer(1, "Base", &base_layer),
+        tile_layer(2, "Rivers", &empty_layer),
+        tile_layer(3, "Roads", &empty_layer),
+        tile_layer(4, "ForestsAndMountains", &empty_layer),
+    );
+    let path = std::env::temp_dir().join(format!("lands_of_mana_bench_{}.tmx", size));
+    fs::write(&path, map).unwrap();
+    path
+}
+
+fn load_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_map");
+    group.sample_size(10);
+    for size in [128, 256, 512] {
+        let path = generate_map(size, 8);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &path, |b, path| {
+            b.iter(|| LoadedMap::read(path).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load_map);
+criterion_main!(benches);
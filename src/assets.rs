@@ -1,13 +1,26 @@
+use std::path::Path;
+
+use bevy::asset::{AssetServerSettings, LoadState};
 use bevy_asset_loader::prelude::*;
 
-use crate::prelude::*;
+use crate::{
+    game::load_map::{
+        map_asset_path, LoadedMap, MapLoadError, MapLoadFailed, MapPath, TmxMap, TmxMapLoader,
+    },
+    prelude::*,
+};
 pub struct AssetLoadingPlugin {}
 
 impl Plugin for AssetLoadingPlugin {
     fn build(&self, app: &mut App) {
+        app.add_asset::<TmxMap>()
+            .init_asset_loader::<TmxMapLoader>()
+            .add_startup_system(register_map_asset)
+            .add_system(watch_map_load.run_in_state(config::EngineState::LoadingAssets));
         app.add_loading_state(
             LoadingState::new(config::EngineState::LoadingAssets)
                 .continue_to_state(config::EngineState::LoadingAssets.next())
+                .with_collection::<MapAssets>()
                 .with_collection::<TileAssets>()
                 .with_collection::<CreatureAssets>()
                 .with_collection::<UiAssets>()
@@ -17,6 +30,55 @@ impl Plugin for AssetLoadingPlugin {
     }
 }
 
+/// Map path is only known after plugins are built (it can come from command
+/// line), so it's registered before loading starts
+fn register_map_asset(
+    map_path_option: Option<Res<MapPath>>,
+    asset_server_settings: Res<AssetServerSettings>,
+    mut dynamic_assets: ResMut<DynamicAssets>,
+) {
+    let map_path = map_path_option.map_or_else(MapPath::default, |map_path| map_path.clone());
+    let path = map_asset_path(&map_path.0, Path::new(&asset_server_settings.asset_folder));
+    dynamic_assets.register_asset(
+        "map",
+        DynamicAsset::File {
+            path: path.to_string_lossy().to_string(),
+        },
+    );
+}
+
+/// Loading state waits for a map that failed to load forever, so the game
+/// goes to the error screen instead
+fn watch_map_load(
+    mut commands: Commands,
+    map_path_option: Option<Res<MapPath>>,
+    asset_server: Res<AssetServer>,
+    asset_server_settings: Res<AssetServerSettings>,
+) {
+    let map_path = map_path_option.map_or_else(MapPath::default, |map_path| map_path.clone());
+    let path = map_asset_path(&map_path.0, Path::new(&asset_server_settings.asset_folder));
+    if asset_server.get_load_state(path.as_path()) != LoadState::Failed {
+        return;
+    }
+    // Asset server only logs why, reading the map again gets the error
+    let error = match LoadedMap::read(&map_path.0) {
+        Err(error @ MapLoadError::Tmx { .. }) => error,
+        _ => MapLoadError::Tmx {
+            path: map_path.0.clone(),
+            message: "asset server couldn't load it".to_string(),
+        },
+    };
+    error!("{}", error);
+    commands.insert_resource(MapLoadFailed(error));
+    commands.insert_resource(NextState(config::EngineState::LoadingFailed));
+}
+
+#[derive(AssetCollection)]
+pub struct MapAssets {
+    #[asset(key = "map")]
+    pub map: Handle<TmxMap>,
+}
+
 #[derive(AssetCollection)]
 pub struct TileAssets {
     // #[asset(path = "tiles", folder(typed))]
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use clap::{CommandFactory, ErrorKind, Parser};
+use lands_of_mana::{
+    game::{
+        save,
+        start::{self, StartError},
+    },
+    headless,
+    options::GameOptions,
+};
+
+/// Runs the game simulation without window or rendering and prints summary
+#[derive(Parser, Debug)]
+#[clap(name = "headless")]
+struct HeadlessOptions {
+    /// Number of days to simulate
+    #[clap(long, default_value_t = 10)]
+    days: u32,
+    /// Save the game here once simulation is done
+    #[clap(long, value_name = "PATH")]
+    save_to: Option<PathBuf>,
+    #[clap(flatten)]
+    game: GameOptions,
+}
+
+fn main() {
+    let mut options = HeadlessOptions::parse();
+    // Headless runs are for testing and balancing, they don't replace the
+    // last recorded game
+    options.game.no_record = true;
+    // Game is ticked every update, days to run are given by `--days`
+    if options.game.skip_to_day.is_some() {
+        HeadlessOptions::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--skip-to-day doesn't apply to headless runs, use --days",
+            )
+            .exit();
+    }
+
+    let mut app = headless::build_app(None);
+    if let Err(error) = start::insert_start_resources(&mut app, &options.game) {
+        let kind = match error {
+            StartError::OnlyForNewGames { .. } => ErrorKind::ArgumentConflict,
+            _ => ErrorKind::ValueValidation,
+        };
+        HeadlessOptions::command().error(kind, error).exit();
+    }
+    // Systems read the seed and player to view as from options
+    app.insert_resource(options.game.clone());
+    if let Err(error) = headless::run_until_in_game(&mut app) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    print!(
+        "{}",
+        headless::SimulationSummary::from_world(&mut app.world)
+    );
+    for _ in 0..options.days {
+        headless::run_days(&mut app, 1);
+        print!(
+            "{}",
+            headless::SimulationSummary::from_world(&mut app.world)
+        );
+    }
+
+    if let Some(save_to) = options.save_to {
+        if let Err(error) =
+            save::SaveGame::from_world(&mut app.world).and_then(|save| save.write(&save_to))
+        {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
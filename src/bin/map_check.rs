@@ -0,0 +1,46 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use lands_of_mana::game::{
+    load_map::{LoadedMap, MapLoadError},
+    map_check::check_map,
+};
+
+/// Loads maps the same way the game does and prints every problem found
+///
+/// Usage: map_check <map.tmx>...
+fn main() -> ExitCode {
+    let paths = std::env::args()
+        .skip(1)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("Usage: map_check <map.tmx>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let problems = match LoadedMap::read(&path) {
+            Ok(map) => check_map(&map).iter().map(ToString::to_string).collect(),
+            Err(MapLoadError::Invalid { problems, .. }) => {
+                problems.iter().map(ToString::to_string).collect()
+            }
+            Err(error) => vec![error.to_string()],
+        };
+        if problems.is_empty() {
+            println!("{}: ok", path.display());
+        } else {
+            failed = true;
+            println!("{}: {} problems", path.display(), problems.len());
+            for problem in problems {
+                println!("  {}", problem);
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::ecs::system::AsSystemLabel;
+use serde::{Deserialize, Serialize};
 use strum_macros::{EnumIter, EnumString};
 
 use crate::prelude::*;
@@ -9,6 +10,7 @@ pub enum EngineState {
     LoadingWorld,
     LoadingGraphics,
     InGame,
+    LoadingFailed, // world couldn't be loaded, error is shown instead
 }
 
 impl EngineState {
@@ -18,6 +20,7 @@ impl EngineState {
             EngineState::LoadingWorld => EngineState::LoadingGraphics,
             EngineState::LoadingGraphics => EngineState::InGame,
             EngineState::InGame => EngineState::InGame,
+            EngineState::LoadingFailed => EngineState::LoadingFailed,
         }
     }
 }
@@ -72,17 +75,21 @@ impl<Marker> OrderedSystemLabel<Marker> for UiSyncLabel where UiSyncLabel: AsSys
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, SystemLabel)]
 
 pub enum GameTickStageLabel {
+    Commands,        // Apply commands players gave since last tick
     Tick,            // Perform game tick update
     UpdateEntities,  // clean up time based things and things that need to removed
     UpdateResources, // update state of resources based on tick, incl upkeep
+    Checksum,        // hash world state after the tick
 }
 
 impl OrderedLabel for GameTickStageLabel {
     fn after(&self) -> Option<GameTickStageLabel> {
         match self {
-            GameTickStageLabel::Tick => None,
+            GameTickStageLabel::Commands => None,
+            GameTickStageLabel::Tick => Some(GameTickStageLabel::Commands),
             GameTickStageLabel::UpdateEntities => Some(GameTickStageLabel::Tick),
             GameTickStageLabel::UpdateResources => Some(GameTickStageLabel::UpdateEntities),
+            GameTickStageLabel::Checksum => Some(GameTickStageLabel::UpdateResources),
         }
     }
 }
@@ -92,6 +99,67 @@ impl<Marker> OrderedSystemLabel<Marker> for GameTickStageLabel where
 {
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, SystemLabel)]
+pub enum CommandsLabel {
+    Apply,   // take commands for this tick, record them and apply unit orders
+    Recruit, // queue units that commands asked cities to recruit
+}
+
+impl OrderedLabel for CommandsLabel {
+    fn after(&self) -> Option<CommandsLabel> {
+        match self {
+            CommandsLabel::Apply => None,
+            CommandsLabel::Recruit => Some(CommandsLabel::Apply),
+        }
+    }
+}
+
+impl<Marker> OrderedSystemLabel<Marker> for CommandsLabel where CommandsLabel: AsSystemLabel<Marker> {}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, SystemLabel)]
+pub enum UpdateEntitiesLabel {
+    Movement,    // execute unit orders
+    Combat,      // resolve battles between units that met after moving
+    Recruitment, // progress recruitment in cities and spawn finished units
+    Control,     // change province owners after capture or occupation
+}
+
+impl OrderedLabel for UpdateEntitiesLabel {
+    fn after(&self) -> Option<UpdateEntitiesLabel> {
+        match self {
+            UpdateEntitiesLabel::Movement => None,
+            UpdateEntitiesLabel::Combat => Some(UpdateEntitiesLabel::Movement),
+            UpdateEntitiesLabel::Recruitment => Some(UpdateEntitiesLabel::Combat),
+            UpdateEntitiesLabel::Control => Some(UpdateEntitiesLabel::Recruitment),
+        }
+    }
+}
+
+impl<Marker> OrderedSystemLabel<Marker> for UpdateEntitiesLabel where
+    UpdateEntitiesLabel: AsSystemLabel<Marker>
+{
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, SystemLabel)]
+pub enum UpdateResourcesLabel {
+    Capacity, // sum up capacity of players
+    Upkeep,   // pay stockpile upkeep and disband units over capacity
+}
+
+impl OrderedLabel for UpdateResourcesLabel {
+    fn after(&self) -> Option<UpdateResourcesLabel> {
+        match self {
+            UpdateResourcesLabel::Capacity => None,
+            UpdateResourcesLabel::Upkeep => Some(UpdateResourcesLabel::Capacity),
+        }
+    }
+}
+
+impl<Marker> OrderedSystemLabel<Marker> for UpdateResourcesLabel where
+    UpdateResourcesLabel: AsSystemLabel<Marker>
+{
+}
+
 pub trait OrderedLabel: Sized {
     fn after(&self) -> Option<Self>;
 }
@@ -112,7 +180,9 @@ impl LabelAndAfter for ConditionSet {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter, EnumString, Default)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter, EnumString, Default, Serialize, Deserialize,
+)]
 pub enum Direction {
     #[default]
     North,
@@ -4,4 +4,10 @@ use leafwing_input_manager::Actionlike;
 pub enum WorldActions {
     Pause,
     Resume,
+    Save,
+    SpeedHalf,
+    SpeedNormal,
+    SpeedDouble,
+    SpeedQuadruple,
+    SpeedMax,
 }
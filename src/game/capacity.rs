@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    game::{
+        units::{Unit, UnitType},
+        world::{
+            CapacityResourceAmount, CapacityResourceProsumer, CapacityResourceType, GameId,
+            OfPlayer,
+        },
+    },
+    prelude::*,
+};
+
+/// Unit was disbanded because its player didn't have enough capacity for it
+#[derive(Debug, Clone, Copy)]
+pub struct UnitDisbanded {
+    pub unit: Entity,
+    pub resource: CapacityResourceType,
+}
+
+/// Sum capacity prosumers of every player into their capacity amounts,
+/// positive prosumers give total and all of them count towards free
+pub fn update_capacity_resources(
+    mut capacity_query: Query<(
+        &OfPlayer,
+        &CapacityResourceType,
+        &mut CapacityResourceAmount,
+    )>,
+    prosumer_query: Query<(&OfPlayer, &CapacityResourceType, &CapacityResourceProsumer)>,
+) {
+    let mut amounts: HashMap<(Entity, CapacityResourceType), CapacityResourceAmount> =
+        HashMap::new();
+    for (OfPlayer(player), resource, CapacityResourceProsumer(amount)) in prosumer_query.iter() {
+        let player_amount = amounts.entry((*player, *resource)).or_default();
+        if *amount >= 0 {
+            player_amount.total += amount;
+        }
+        player_amount.free += amount;
+    }
+    for (OfPlayer(player), resource, mut capacity_amount) in capacity_query.iter_mut() {
+        let new_amount = amounts
+            .get(&(*player, *resource))
+            .copied()
+            .unwrap_or_default();
+        // Avoid triggering change detection when nothing changed
+        if *capacity_amount != new_amount {
+            *capacity_amount = new_amount;
+        }
+    }
+}
+
+/// Players can't keep more units than their capacity allows, so when free
+/// capacity goes negative (eg city that provided it was lost) units that
+/// use it are disbanded, newest first, until capacity is not negative.
+/// Disbanded unit frees every capacity it used, not only the one that was
+/// over.
+pub fn disband_over_capacity_units(
+    mut commands: Commands,
+    mut disband_events: EventWriter<UnitDisbanded>,
+    mut capacity_query: Query<(
+        &OfPlayer,
+        &CapacityResourceType,
+        &mut CapacityResourceAmount,
+    )>,
+    unit_query: Query<(Entity, &GameId, &OfPlayer, &UnitType), With<Unit>>,
+) {
+    let mut player_capacities: HashMap<
+        Entity,
+        BTreeMap<CapacityResourceType, Mut<CapacityResourceAmount>>,
+    > = HashMap::new();
+    for (OfPlayer(player), resource, capacity_amount) in capacity_query.iter_mut() {
+        player_capacities
+            .entry(*player)
+            .or_default()
+            .insert(*resource, capacity_amount);
+    }
+    // Entity indices are reused, ids only grow, so newest unit has the biggest id
+    let mut units = unit_query.iter().collect::<Vec<_>>();
+    units.sort_unstable_by_key(|(_, id, _, _)| std::cmp::Reverse(**id));
+    let mut disbanded = HashSet::new();
+    for (player, capacities) in player_capacities.iter_mut() {
+        let over_capacity = capacities
+            .iter()
+            .filter(|(_, capacity_amount)| capacity_amount.free < 0)
+            .map(|(resource, _)| *resource)
+            .collect::<Vec<_>>();
+        for resource in over_capacity {
+            for (unit_entity, _, OfPlayer(unit_player), unit_type) in units.iter() {
+                if capacities[&resource].free >= 0 {
+                    break;
+                }
+                if unit_player != player || disbanded.contains(unit_entity) {
+                    continue;
+                }
+                let capacity_cost = unit_type.get_unit_stats().capacity_cost;
+                if !matches!(capacity_cost.get(&resource), Some(cost) if *cost < 0) {
+                    continue;
+                }
+                for (cost_resource, cost) in capacity_cost.iter() {
+                    if let Some(capacity_amount) = capacities.get_mut(cost_resource) {
+                        capacity_amount.free -= cost;
+                    }
+                }
+                disbanded.insert(*unit_entity);
+                commands.entity(*unit_entity).despawn_recursive();
+                disband_events.send(UnitDisbanded {
+                    unit: *unit_entity,
+                    resource,
+                });
+            }
+        }
+    }
+}
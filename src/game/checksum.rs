@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    game::{
+        map::Position,
+        province::{City, CityType, Province, ProvinceOccupation},
+        random::GameRng,
+        recruitment::RecruitmentQueue,
+        site::{Site, SiteType},
+        units::{Unit, UnitFigure, UnitFigureHealth, UnitOrders, UnitType},
+        world::{GameId, OfPlayer, Player, StockpileResourceAmount, StockpileResourceType},
+        GameDay, GameTick, GameWorld,
+    },
+    prelude::*,
+};
+
+/// FNV-1a, as std hashers don't promise to hash the same way between Rust
+/// versions
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash of everything that affects how the game goes on, computed after
+/// every tick. Two runs of the same game have same checksums on the same
+/// platform, so the first tick where they differ is where they diverged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StateChecksum {
+    pub day: u32,
+    pub tick: usize,
+    pub checksum: u64,
+}
+
+impl std::fmt::Display for StateChecksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Day {:04} Tick {:02} checksum {:016x}",
+            self.day + 1,
+            self.tick + 1,
+            self.checksum
+        )
+    }
+}
+
+impl StateChecksum {
+    /// Everything is hashed in `GameId` order, never in the order entities
+    /// are stored in
+    pub fn from_world(world: &mut World) -> StateChecksum {
+        let (day, tick) = world
+            .query_filtered::<(&GameDay, &GameTick), With<GameWorld>>()
+            .iter(world)
+            .next()
+            .map(|(GameDay(day), GameTick(tick))| (*day, *tick))
+            .expect("Game world should be loaded before checksum");
+        let mut hasher = StableHasher::default();
+        day.hash(&mut hasher);
+        tick.hash(&mut hasher);
+        world.get_resource::<GameRng>().hash(&mut hasher);
+
+        let player_ids = world
+            .query_filtered::<(Entity, &GameId), With<Player>>()
+            .iter(world)
+            .map(|(entity, id)| (entity, *id))
+            .collect::<HashMap<_, _>>();
+        let player_id = |OfPlayer(player): &OfPlayer| player_ids.get(player).copied();
+
+        let mut stockpiles = world
+            .query::<(&OfPlayer, &StockpileResourceType, &StockpileResourceAmount)>()
+            .iter(world)
+            .map(|(player, resource, StockpileResourceAmount(amount))| {
+                (player_id(player), *resource, amount.to_bits())
+            })
+            .collect::<Vec<_>>();
+        stockpiles.sort_unstable();
+        stockpiles.hash(&mut hasher);
+
+        let mut figure_query = world.query::<(&UnitFigure, &UnitFigureHealth)>();
+        let mut units = world
+            .query_filtered::<(
+                &GameId,
+                &OfPlayer,
+                &UnitType,
+                &Position,
+                &UnitOrders,
+                &Children,
+            ), With<Unit>>()
+            .iter(world)
+            .map(|(id, player, unit_type, position, orders, children)| {
+                let mut figures = children
+                    .iter()
+                    .filter_map(|child| {
+                        figure_query
+                            .get(world, *child)
+                            .ok()
+                            .map(|(figure, UnitFigureHealth(health))| (figure.index, *health))
+                    })
+                    .collect::<Vec<_>>();
+                figures.sort_unstable();
+                let mut unit_hasher = StableHasher::default();
+                (player_id(player), unit_type, position, orders, figures).hash(&mut unit_hasher);
+                (*id, unit_hasher.finish())
+            })
+            .collect::<Vec<_>>();
+        units.sort_unstable();
+        units.hash(&mut hasher);
+
+        let mut cities = world
+            .query_filtered::<(&GameId, Option<&OfPlayer>, &CityType, &RecruitmentQueue), With<City>>()
+            .iter(world)
+            .map(|(id, player_option, city_type, recruitment)| {
+                let mut city_hasher = StableHasher::default();
+                (player_option.and_then(player_id), city_type, recruitment).hash(&mut city_hasher);
+                (*id, city_hasher.finish())
+            })
+            .collect::<Vec<_>>();
+        cities.sort_unstable();
+        cities.hash(&mut hasher);
+
+        let mut sites = world
+            .query_filtered::<(&GameId, Option<&OfPlayer>, &SiteType), With<Site>>()
+            .iter(world)
+            .map(|(id, player_option, site_type)| {
+                (*id, player_option.and_then(player_id), *site_type)
+            })
+            .collect::<Vec<_>>();
+        sites.sort_unstable_by_key(|(id, _, _)| *id);
+        sites.hash(&mut hasher);
+
+        let mut provinces = world
+            .query::<(&Province, Option<&OfPlayer>, &ProvinceOccupation)>()
+            .iter(world)
+            .map(|(province, owner_option, occupation)| {
+                (
+                    province.object_id,
+                    owner_option.and_then(player_id),
+                    occupation
+                        .player
+                        .and_then(|player| player_ids.get(&player).copied()),
+                    occupation.ticks,
+                )
+            })
+            .collect::<Vec<_>>();
+        provinces.sort_unstable_by_key(|(object_id, _, _, _)| *object_id);
+        provinces.hash(&mut hasher);
+
+        StateChecksum {
+            day,
+            tick,
+            checksum: hasher.finish(),
+        }
+    }
+}
+
+/// Compute checksum at the end of the tick, once everything has changed
+pub fn update_state_checksum(
+    mut commands: Commands,
+    game_tick_query: Query<ChangeTrackers<GameTick>>,
+) {
+    if !game_tick_query.single().is_changed() {
+        return;
+    }
+    commands.add(|world: &mut World| {
+        let checksum = StateChecksum::from_world(world);
+        debug!("{}", checksum);
+        world.insert_resource(checksum);
+    });
+}
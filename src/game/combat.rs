@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::{
+    game::{
+        map::Position,
+        units::{Unit, UnitFigure, UnitFigureHealth, UnitType},
+        world::{GameId, OfPlayer},
+        GameTick,
+    },
+    prelude::*,
+};
+
+/// Things that happened in battle during a tick, so that renderer can animate them
+#[derive(Debug, Clone)]
+pub enum BattleEvent {
+    Attack {
+        figure: Entity,
+    },
+    Hit {
+        figure: Entity,
+        damage: u32,
+    },
+    // Figure is despawned by the time event is read, so it carries everything
+    // needed to show it dying
+    FigureDied {
+        unit_type: UnitType,
+        position: Position,
+        index: usize,
+    },
+    UnitDestroyed {
+        unit: Entity,
+    },
+}
+
+struct Combatant {
+    unit: Entity,
+    id: GameId,
+    player: Entity,
+    position: Position,
+    unit_type: UnitType,
+    // living figures, ordered by index
+    figures: Vec<Entity>,
+}
+
+type CombatUnitQuery = (
+    Entity,
+    &'static GameId,
+    &'static OfPlayer,
+    &'static Position,
+    &'static UnitType,
+    &'static Children,
+);
+
+/// Units of different players that share or are adjacent to a tile fight.
+/// Every tick each figure hits one figure of the closest enemy unit, damage
+/// is applied at once so the order units are processed in doesn't matter.
+pub fn unit_combat(
+    mut commands: Commands,
+    mut battle_events: EventWriter<BattleEvent>,
+    game_tick_query: Query<ChangeTrackers<GameTick>>,
+    unit_query: Query<CombatUnitQuery, With<Unit>>,
+    mut figure_query: Query<(&UnitFigure, &mut UnitFigureHealth)>,
+) {
+    if !game_tick_query.single().is_changed() {
+        return;
+    }
+
+    let combatants = unit_query
+        .iter()
+        .map(
+            |(unit, id, OfPlayer(player), position, unit_type, children)| {
+                let mut figures = children
+                    .iter()
+                    .filter_map(|child| {
+                        figure_query
+                            .get(*child)
+                            .ok()
+                            .map(|(figure, _)| (figure.index, *child))
+                    })
+                    .collect::<Vec<_>>();
+                figures.sort_unstable_by_key(|(index, _)| *index);
+                Combatant {
+                    unit,
+                    id: *id,
+                    player: *player,
+                    position: *position,
+                    unit_type: *unit_type,
+                    figures: figures.into_iter().map(|(_, figure)| figure).collect(),
+                }
+            },
+        )
+        .filter(|combatant| !combatant.figures.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut damage_by_figure: HashMap<Entity, u32> = HashMap::new();
+    for attacker in combatants.iter() {
+        let target_option = combatants
+            .iter()
+            .filter(|defender| {
+                defender.player != attacker.player
+                    && defender.position.distance(&attacker.position) <= 1
+            })
+            .min_by_key(|defender| {
+                (
+                    defender.position.distance(&attacker.position),
+                    defender.position.x,
+                    defender.position.y,
+                    defender.id,
+                )
+            });
+        if let Some(target) = target_option {
+            let damage = attacker.unit_type.get_unit_stats().damage;
+            for (index, figure) in attacker.figures.iter().enumerate() {
+                let target_figure = target.figures[index % target.figures.len()];
+                *damage_by_figure.entry(target_figure).or_insert(0) += damage;
+                battle_events.send(BattleEvent::Attack { figure: *figure });
+            }
+        }
+    }
+
+    for combatant in combatants.iter() {
+        let mut living_figures = combatant.figures.len();
+        for figure_entity in combatant.figures.iter() {
+            if let Some(damage) = damage_by_figure.get(figure_entity) {
+                let (figure, mut health) = figure_query.get_mut(*figure_entity).unwrap();
+                health.0 = health.0.saturating_sub(*damage);
+                battle_events.send(BattleEvent::Hit {
+                    figure: *figure_entity,
+                    damage: *damage,
+                });
+                if health.0 == 0 {
+                    living_figures -= 1;
+                    battle_events.send(BattleEvent::FigureDied {
+                        unit_type: combatant.unit_type,
+                        position: combatant.position,
+                        index: figure.index,
+                    });
+                    commands.entity(*figure_entity).despawn_recursive();
+                }
+            }
+        }
+        if living_figures == 0 {
+            // Capacity prosumers are unit children, so they go away with it
+            battle_events.send(BattleEvent::UnitDestroyed {
+                unit: combatant.unit,
+            });
+            commands.entity(combatant.unit).despawn_recursive();
+        }
+    }
+}
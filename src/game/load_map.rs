@@ -1,158 +1,1155 @@
-use std::{path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
+use bevy::{
+    asset::{AssetLoader, AssetServerSettings, BoxedFuture, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+};
 use euclid::point2;
-use fart_2d_geom::ConvexPolygon;
 use num_traits::FromPrimitive;
 use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
 use tiled::{
-    FiniteTileLayer, LayerType, Loader, Map, ObjectLayer, ObjectShape, PropertyValue, TileLayer,
+    FiniteTileLayer, LayerTile, LayerType, Loader, Map, ObjectLayer, ObjectShape, PropertyValue,
+    TileLayer,
 };
 
 use super::{
     map::{ForestType, MountainType, RoadType, TerrainTop},
-    province::CityBundle,
+    polygon::{Polygon, PolygonError},
+    province::{City, CityBundle, CityType, InProvince, Province, ProvinceOccupation},
+    province_graph::ProvinceGraph,
+    province_index::ProvinceIndex,
+    setup::{GameSetup, GameSetupError},
+    site::{Site, SiteBundle, SiteType},
+    world::{GameId, GameIds, OfPlayer, Player, PlayerName},
 };
 use crate::{
-    game::map::{Position, TerrainBase, TerrainBundle, TerrainType},
+    assets::MapAssets,
+    game::map::{MapTerrain, Position, TerrainBase, TerrainBundle, TerrainType},
+    options::GameOptions,
     prelude::*,
 };
 
+/// Path to the TMX map that `load_map` will load
+#[derive(Debug, Clone)]
+pub struct MapPath(pub PathBuf);
+
+impl Default for MapPath {
+    fn default() -> Self {
+        MapPath(PathBuf::from("./assets/maps/world_of_magic.tmx"))
+    }
+}
+
+/// TMX map loaded by asset server
+#[derive(Debug, TypeUuid)]
+#[uuid = "5b0c1a4e-8f2d-4c67-9e3a-6d1f0b7c2e94"]
+pub struct TmxMap {
+    // Path on disk, tilesets and setup file are relative to it
+    pub path: PathBuf,
+    pub map: Map,
+}
+
+pub struct TmxMapLoader {
+    asset_folder: PathBuf,
+}
+
+impl FromWorld for TmxMapLoader {
+    fn from_world(world: &mut World) -> Self {
+        let asset_folder = world.get_resource::<AssetServerSettings>().map_or_else(
+            || AssetServerSettings::default().asset_folder,
+            |settings| settings.asset_folder.clone(),
+        );
+        TmxMapLoader {
+            asset_folder: PathBuf::from(asset_folder),
+        }
+    }
+}
+
+impl AssetLoader for TmxMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            // Tiled reads tilesets from disk itself
+            let path = self.asset_folder.join(load_context.path());
+            let map = Loader::new().load_tmx_map_from(bytes, &path)?;
+            load_context.set_default_asset(LoadedAsset::new(TmxMap { path, map }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+/// Path of the map for asset server, which wants it relative to the asset
+/// folder (eg `../maps/world_of_magic.tmx`)
+pub fn map_asset_path(map_path: &Path, asset_folder: &Path) -> PathBuf {
+    let normal_components = |path: &Path| {
+        path.components()
+            .filter(|component| *component != std::path::Component::CurDir)
+            .collect::<Vec<_>>()
+    };
+    let map_components = normal_components(map_path);
+    let folder_components = normal_components(asset_folder);
+    let common = map_components
+        .iter()
+        .zip(folder_components.iter())
+        .take_while(|(map_component, folder_component)| map_component == folder_component)
+        .count();
+    folder_components[common..]
+        .iter()
+        .map(|_| std::path::Component::ParentDir)
+        .chain(map_components[common..].iter().copied())
+        .collect()
+}
+
+#[derive(Debug, Error)]
+pub enum MapLoadError {
+    #[error("couldn't load map {path}: {message}")]
+    Tmx { path: PathBuf, message: String },
+    #[error("map {path} has {} problems:\n{}", .problems.len(), problems_list(.problems))]
+    Invalid {
+        path: PathBuf,
+        problems: Vec<MapProblem>,
+    },
+}
+
+fn problems_list(problems: &[MapProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("- {}", problem))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Something wrong in the map file. Tile coordinates are as in Tiled, with
+/// y going down, not game positions.
+#[derive(Debug, Error)]
+pub enum MapProblem {
+    #[error("layer {layer} is missing")]
+    MissingLayer { layer: String },
+    #[error("layer {layer} should be {expected} layer")]
+    WrongLayerType {
+        layer: String,
+        expected: &'static str,
+    },
+    #[error("province object {object_id} is not a polygon")]
+    ProvinceNotPolygon { object_id: u32 },
+    #[error("province object {object_id} polygon doesn't have an area")]
+    InvalidProvincePolygon { object_id: u32 },
+    #[error("province object {object_id} polygon has edges crossing each other")]
+    SelfIntersectingProvince { object_id: u32 },
+    #[error("province object {object_id} is a hole in {province_id}, which is not a province")]
+    UnknownProvinceForHole { object_id: u32, province_id: u32 },
+    #[error("tile {x},{y} is missing in layer {layer}")]
+    MissingTile { layer: String, x: u32, y: u32 },
+    #[error("tile {x},{y} in layer {layer} has unknown tile id {tile_id}")]
+    UnknownTile {
+        layer: String,
+        x: u32,
+        y: u32,
+        tile_id: u32,
+    },
+    #[error("tile {x},{y} in layer {layer} has unknown {property} {value}")]
+    UnknownTileType {
+        layer: String,
+        x: u32,
+        y: u32,
+        property: &'static str,
+        value: String,
+    },
+    #[error("tile {x},{y} in layer {layer} has no {property} and its tileset has no types")]
+    MissingTileType {
+        layer: String,
+        x: u32,
+        y: u32,
+        property: &'static str,
+    },
+    #[error("tile {x},{y} is not in any province")]
+    TileOutsideProvinces { x: u32, y: u32 },
+    #[error("city object {object_id} ({name}) doesn't have city_type")]
+    MissingCityType { object_id: u32, name: String },
+    #[error("city object {object_id} ({name}) has unknown city_type {value}")]
+    UnknownCityType {
+        object_id: u32,
+        name: String,
+        value: String,
+    },
+    #[error("city object {object_id} ({name}) is not in any province")]
+    CityOutsideProvinces { object_id: u32, name: String },
+    #[error("site object {object_id} ({name}) doesn't have site_type")]
+    MissingSiteType { object_id: u32, name: String },
+    #[error("site object {object_id} ({name}) has unknown site_type {value}")]
+    UnknownSiteType {
+        object_id: u32,
+        name: String,
+        value: String,
+    },
+    #[error("site object {object_id} ({name}) is not in any province")]
+    SiteOutsideProvinces { object_id: u32, name: String },
+    #[error("site object {object_id} ({name}) is outside the map")]
+    SiteOutsideMap { object_id: u32, name: String },
+    #[error("{0}")]
+    Setup(GameSetupError),
+    #[error("object {object_id} ({name}) has player {value}, who is not in the game setup")]
+    UnknownPlayer {
+        object_id: u32,
+        name: String,
+        value: String,
+    },
+    #[error("tile {x},{y} is in provinces {object_ids:?}")]
+    OverlappingProvinces {
+        x: u32,
+        y: u32,
+        object_ids: Vec<u32>,
+    },
+    #[error("city object {object_id} ({name}) doesn't fit on the map")]
+    CityOutsideMap { object_id: u32, name: String },
+    #[error("city object {object_id} ({name}) is on water at tile {x},{y}")]
+    CityOnWater {
+        object_id: u32,
+        name: String,
+        x: u32,
+        y: u32,
+    },
+    #[error("land region of {tiles} tiles at tile {x},{y} can't be reached from the rest")]
+    UnreachableLand { tiles: usize, x: u32, y: u32 },
+}
+
+/// Load of the map failed, game shows the error instead of starting
+pub struct MapLoadFailed(pub MapLoadError);
+
+pub struct MapProvince {
+    pub object_id: u32,
+    pub name: String,
+    pub region: Option<String>,
+    // Name of the player from `player` property
+    pub player: Option<String>,
+    pub polygon: Polygon,
+}
+
+pub struct MapTile {
+    // Index in `provinces`
+    pub province: usize,
+    pub position: Position,
+    pub base: TerrainType,
+    pub top: TerrainTop,
+}
+
+pub struct MapCity {
+    pub object_id: u32,
+    pub name: String,
+    // Index in `provinces`
+    pub province: usize,
+    pub position: Position,
+    pub city_type: CityType,
+    // Name of the player from `player` property
+    pub player: Option<String>,
+}
+
+pub struct MapSite {
+    pub object_id: u32,
+    pub name: String,
+    // Index in `provinces`
+    pub province: usize,
+    pub position: Position,
+    pub site_type: SiteType,
+    // Name of the player from `player` property
+    pub player: Option<String>,
+}
+
+/// Everything game needs from the TMX map, read and checked before anything
+/// is spawned
+pub struct LoadedMap {
+    pub width: u32,
+    pub height: u32,
+    pub provinces: Vec<MapProvince>,
+    pub terrain: Vec<MapTile>,
+    pub cities: Vec<MapCity>,
+    pub sites: Vec<MapSite>,
+    // Setup file referenced by the map
+    pub setup: Option<GameSetup>,
+}
+
+impl LoadedMap {
+    /// Spawn provinces with their terrain as children of the world, together
+    /// with map components. Returns province entities in the same order as
+    /// in `provinces`.
+    pub fn spawn_terrain(&self, commands: &mut Commands, world_entity: Entity) -> Vec<Entity> {
+        commands.entity(world_entity).insert(game::map::Map {
+            width: self.width,
+            height: self.height,
+        });
+        let mut map_terrain = MapTerrain::new(self.width, self.height);
+
+        let province_entities = self
+            .provinces
+            .iter()
+            .map(|province| {
+                let province_entity = commands
+                    .spawn()
+                    .insert(Province {
+                        object_id: province.object_id,
+                        name: province.name.clone(),
+                        region: province.region.clone(),
+                    })
+                    .insert(ProvinceOccupation::default())
+                    .id();
+                commands.entity(world_entity).add_child(province_entity);
+                province_entity
+            })
+            .collect::<Vec<_>>();
+
+        for tile in self.terrain.iter() {
+            let province_entity = province_entities[tile.province];
+            map_terrain.set(&tile.position, tile.base, tile.top);
+            let terrain = commands
+                .spawn_bundle(TerrainBundle {
+                    province: InProvince(province_entity),
+                    position: tile.position,
+                    base: TerrainBase(tile.base),
+                    top: tile.top,
+                    ..Default::default()
+                })
+                .id();
+            commands.entity(province_entity).add_child(terrain);
+        }
+
+        let province_graph = ProvinceGraph::new(
+            &map_terrain,
+            &self
+                .provinces
+                .iter()
+                .map(|province| province.object_id)
+                .zip(province_entities.iter().copied())
+                .collect::<Vec<_>>(),
+            self.terrain
+                .iter()
+                .map(|tile| (tile.position, tile.province)),
+        );
+        commands
+            .entity(world_entity)
+            .insert(map_terrain)
+            .insert(province_graph);
+        province_entities
+    }
+
+    /// `player` properties of cities, sites and provinces that name a player
+    /// who isn't in the setup
+    pub fn player_problems(&self, setup: &GameSetup) -> Vec<MapProblem> {
+        let cities = self
+            .cities
+            .iter()
+            .map(|city| (city.object_id, &city.name, &city.player));
+        let sites = self
+            .sites
+            .iter()
+            .map(|site| (site.object_id, &site.name, &site.player));
+        let provinces = self
+            .provinces
+            .iter()
+            .map(|province| (province.object_id, &province.name, &province.player));
+        cities
+            .chain(sites)
+            .chain(provinces)
+            .filter_map(|(object_id, name, player)| {
+                let player_name = player.as_ref()?;
+                (!setup
+                    .players
+                    .iter()
+                    .any(|player| player.name == *player_name))
+                .then(|| MapProblem::UnknownPlayer {
+                    object_id,
+                    name: name.clone(),
+                    value: player_name.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Read the map from disk, collecting all problems instead of stopping
+    /// at the first
+    pub fn read(path: &Path) -> Result<LoadedMap, MapLoadError> {
+        let mut loader = Loader::new();
+        let map = loader
+            .load_tmx_map(path)
+            .map_err(|error| MapLoadError::Tmx {
+                path: path.to_path_buf(),
+                message: error.to_string(),
+            })?;
+        LoadedMap::from_map(&map, path)
+    }
+
+    /// Read the map that asset server loaded
+    pub fn from_tmx_map(tmx_map: &TmxMap) -> Result<LoadedMap, MapLoadError> {
+        LoadedMap::from_map(&tmx_map.map, &tmx_map.path)
+    }
+
+    fn from_map(map: &Map, path: &Path) -> Result<LoadedMap, MapLoadError> {
+        let mut problems = Vec::new();
+        let loaded_map = LoadedMap::from_tmx(map, path, &mut problems);
+        match loaded_map {
+            Some(loaded_map) if problems.is_empty() => Ok(loaded_map),
+            _ => Err(MapLoadError::Invalid {
+                path: path.to_path_buf(),
+                problems,
+            }),
+        }
+    }
+
+    /// None if map is so broken that it can't be read further
+    fn from_tmx(map: &Map, path: &Path, problems: &mut Vec<MapProblem>) -> Option<LoadedMap> {
+        let setup = GameSetup::from_map(map, path).and_then(|result| {
+            result
+                .map_err(|error| problems.push(MapProblem::Setup(error)))
+                .ok()
+        });
+
+        let base_layer = get_tile_layer(map, TileLayerName::Base, problems);
+        let rivers_layer = get_tile_layer(map, TileLayerName::Rivers, problems);
+        let roads_layer = get_tile_layer(map, TileLayerName::Roads, problems);
+        let forests_and_mountains_layer =
+            get_tile_layer(map, TileLayerName::ForestsAndMountains, problems);
+        let province_layer = get_object_layer(map, ObjectLayerName::Provinces, problems);
+        let cities_layer = get_object_layer(map, ObjectLayerName::Cities, problems);
+        // Maps made before sites don't have the layer, they just have no sites
+        let sites_layer = map
+            .layers()
+            .any(|layer| layer.name == ObjectLayerName::Sites.to_string())
+            .then(|| get_object_layer(map, ObjectLayerName::Sites, problems))
+            .flatten();
+        let (
+            base_layer,
+            rivers_layer,
+            roads_layer,
+            forests_and_mountains_layer,
+            province_layer,
+            cities_layer,
+        ) = match (
+            base_layer,
+            rivers_layer,
+            roads_layer,
+            forests_and_mountains_layer,
+            province_layer,
+            cities_layer,
+        ) {
+            (
+                Some(base),
+                Some(rivers),
+                Some(roads),
+                Some(forests),
+                Some(provinces),
+                Some(cities),
+            ) => (base, rivers, roads, forests, provinces, cities),
+            _ => return None,
+        };
+        let width = base_layer.width();
+        let height = base_layer.height();
+
+        let mut provinces = Vec::new();
+        let mut holes = Vec::new();
+        for province in province_layer.objects() {
+            let object_id = province.id();
+            let points = match &province.shape {
+                ObjectShape::Polygon { points } => points
+                    .iter()
+                    .map(|(x, y)| point2(province.x + *x, province.y + *y))
+                    .collect::<Vec<_>>(),
+                _ => {
+                    problems.push(MapProblem::ProvinceNotPolygon { object_id });
+                    continue;
+                }
+            };
+            // Objects with `hole_in` property are cut out of the province
+            // they point to instead of being provinces
+            match province.properties.get("hole_in") {
+                Some(PropertyValue::ObjectValue(province_id)) => {
+                    holes.push((object_id, *province_id, points));
+                    continue;
+                }
+                Some(PropertyValue::IntValue(province_id)) => {
+                    holes.push((object_id, *province_id as u32, points));
+                    continue;
+                }
+                _ => {}
+            }
+            let string_property = |property: &str| match province.properties.get(property) {
+                Some(PropertyValue::StringValue(value)) if !value.is_empty() => Some(value.clone()),
+                _ => None,
+            };
+            let name = if province.name.is_empty() {
+                string_property("name").unwrap_or_else(|| generate_province_name(object_id))
+            } else {
+                province.name.clone()
+            };
+            match Polygon::new(points) {
+                Ok(polygon) => provinces.push(MapProvince {
+                    object_id,
+                    name,
+                    region: string_property("region"),
+                    player: string_property("player"),
+                    polygon,
+                }),
+                Err(error) => problems.push(polygon_problem(object_id, error)),
+            }
+        }
+        for (object_id, province_id, points) in holes {
+            match provinces
+                .iter_mut()
+                .find(|province| province.object_id == province_id)
+            {
+                Some(province) => {
+                    if let Err(error) = province.polygon.add_hole(points) {
+                        problems.push(polygon_problem(object_id, error));
+                    }
+                }
+                None => problems.push(MapProblem::UnknownProvinceForHole {
+                    object_id,
+                    province_id,
+                }),
+            }
+        }
+        let province_index = ProvinceIndex::new(&provinces, width, height);
+        let province_at = |x: f32, y: f32| province_index.province_at(&provinces, point2(x, y));
+
+        let mut terrain = Vec::new();
+        for map_x in 0..width {
+            for map_y in 0..height {
+                let tile = match base_layer.get_tile(map_x as i32, map_y as i32) {
+                    Some(tile) => tile,
+                    None => {
+                        problems.push(MapProblem::MissingTile {
+                            layer: TileLayerName::Base.to_string(),
+                            x: map_x,
+                            y: map_y,
+                        });
+                        continue;
+                    }
+                };
+                let base = match TerrainType::from_u32(tile.id()) {
+                    Some(base) => base,
+                    None => {
+                        problems.push(MapProblem::UnknownTile {
+                            layer: TileLayerName::Base.to_string(),
+                            x: map_x,
+                            y: map_y,
+                            tile_id: tile.id(),
+                        });
+                        continue;
+                    }
+                };
+                let river = rivers_layer.get_tile(map_x as i32, map_y as i32);
+                let road = roads_layer.get_tile(map_x as i32, map_y as i32);
+                let forest_and_mountain =
+                    forests_and_mountains_layer.get_tile(map_x as i32, map_y as i32);
+                if let Some(tile) = &forest_and_mountain {
+                    if tile.id() >= tile.get_tileset().tilecount {
+                        problems.push(MapProblem::UnknownTile {
+                            layer: TileLayerName::ForestsAndMountains.to_string(),
+                            x: map_x,
+                            y: map_y,
+                            tile_id: tile.id(),
+                        });
+                    }
+                }
+                let mut subtype_problem =
+                    |layer: TileLayerName, (property, value): (&'static str, Option<String>)| {
+                        problems.push(match value {
+                            Some(value) => MapProblem::UnknownTileType {
+                                layer: layer.to_string(),
+                                x: map_x,
+                                y: map_y,
+                                property,
+                                value,
+                            },
+                            None => MapProblem::MissingTileType {
+                                layer: layer.to_string(),
+                                x: map_x,
+                                y: map_y,
+                                property,
+                            },
+                        })
+                    };
+                let road_type = road.map(|tile| {
+                    road_type(&tile).unwrap_or_else(|problem| {
+                        subtype_problem(TileLayerName::Roads, problem);
+                        RoadType::Path
+                    })
+                });
+                let top = match (river, road_type, forest_and_mountain) {
+                    (Some(_), None, _) => TerrainTop::River,
+                    (None, Some(road_type), _) => TerrainTop::Road(road_type),
+                    (Some(_), Some(road_type), _) => TerrainTop::RiverWithBridge(road_type),
+                    (_, _, Some(tile)) => forest_or_mountain(&tile).unwrap_or_else(|problem| {
+                        subtype_problem(TileLayerName::ForestsAndMountains, problem);
+                        TerrainTop::Forest(ForestType::Pine)
+                    }),
+                    _ => TerrainTop::None,
+                };
+                match province_at((map_x * 16) as f32 + 8., (map_y * 16) as f32 + 8.) {
+                    Some(province) => terrain.push(MapTile {
+                        province,
+                        position: Position::new(map_x, height - map_y - 1),
+                        base,
+                        top,
+                    }),
+                    None => problems.push(MapProblem::TileOutsideProvinces { x: map_x, y: map_y }),
+                }
+            }
+        }
+
+        let mut cities = Vec::new();
+        for city in cities_layer.objects() {
+            let city_type = match city.properties.get("city_type") {
+                Some(PropertyValue::StringValue(city_type_str)) => {
+                    match CityType::from_str(city_type_str) {
+                        Ok(city_type) => city_type,
+                        Err(_) => {
+                            problems.push(MapProblem::UnknownCityType {
+                                object_id: city.id(),
+                                name: city.name.clone(),
+                                value: city_type_str.clone(),
+                            });
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    problems.push(MapProblem::MissingCityType {
+                        object_id: city.id(),
+                        name: city.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            let position = match object_position(city.x, city.y, width, height) {
+                Some(position) => position,
+                None => {
+                    problems.push(MapProblem::CityOutsideMap {
+                        object_id: city.id(),
+                        name: city.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            let province = match province_at(city.x, city.y) {
+                Some(province) => province,
+                None => {
+                    problems.push(MapProblem::CityOutsideProvinces {
+                        object_id: city.id(),
+                        name: city.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            let player = match city.properties.get("player") {
+                Some(PropertyValue::StringValue(player_name)) => Some(player_name.clone()),
+                _ => None,
+            };
+            cities.push(MapCity {
+                object_id: city.id(),
+                name: city.name.clone(),
+                province,
+                position,
+                city_type,
+                player,
+            });
+        }
+
+        let mut sites = Vec::new();
+        for site in sites_layer.iter().flat_map(|layer| layer.objects()) {
+            let site_type = match site.properties.get("site_type") {
+                Some(PropertyValue::StringValue(site_type_str)) => {
+                    match SiteType::from_str(site_type_str) {
+                        Ok(site_type) => site_type,
+                        Err(_) => {
+                            problems.push(MapProblem::UnknownSiteType {
+                                object_id: site.id(),
+                                name: site.name.clone(),
+                                value: site_type_str.clone(),
+                            });
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    problems.push(MapProblem::MissingSiteType {
+                        object_id: site.id(),
+                        name: site.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            // Tile objects are placed by their bottom left corner
+            let site_y = if site.tile_data().is_some() {
+                site.y - 16.
+            } else {
+                site.y
+            };
+            let position = match object_position(site.x, site_y, width, height) {
+                Some(position) => position,
+                None => {
+                    problems.push(MapProblem::SiteOutsideMap {
+                        object_id: site.id(),
+                        name: site.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            let province = match province_at(site.x, site_y) {
+                Some(province) => province,
+                None => {
+                    problems.push(MapProblem::SiteOutsideProvinces {
+                        object_id: site.id(),
+                        name: site.name.clone(),
+                    });
+                    continue;
+                }
+            };
+            let player = match site.properties.get("player") {
+                Some(PropertyValue::StringValue(player_name)) => Some(player_name.clone()),
+                _ => None,
+            };
+            sites.push(MapSite {
+                object_id: site.id(),
+                name: site.name.clone(),
+                province,
+                position,
+                site_type,
+                player,
+            });
+        }
+
+        Some(LoadedMap {
+            width,
+            height,
+            provinces,
+            terrain,
+            cities,
+            sites,
+            setup,
+        })
+    }
+}
+
+/// Spawn the world from the map. Map comes from `MapAssets` when assets
+/// were loaded, headless app reads it from disk.
+#[allow(clippy::too_many_arguments)]
 pub fn load_map(
     mut commands: Commands,
+    map_path: Res<MapPath>,
+    map_assets_option: Option<Res<MapAssets>>,
+    tmx_maps_option: Option<Res<Assets<TmxMap>>>,
+    game_setup_option: Option<Res<GameSetup>>,
+    options_option: Option<Res<GameOptions>>,
+    mut game_ids: ResMut<GameIds>,
     world_query: Query<Entity, With<game::GameWorld>>,
-    player_query: Query<Entity, With<game::world::Player>>,
 ) {
-    let mut loader = Loader::new();
-    let map = loader
-        .load_tmx_map(Path::new("./assets/maps/world_of_magic.tmx"))
-        .unwrap();
-
-    let base_layer = get_tile_layer(&map, TileLayerName::Base);
-    let rivers_layer = get_tile_layer(&map, TileLayerName::Rivers);
-    let roads_layer = get_tile_layer(&map, TileLayerName::Roads);
-    let forests_and_mountains_layer = get_tile_layer(&map, TileLayerName::ForestsAndMountains);
-    let width = base_layer.width();
-    let height = base_layer.height();
+    let tmx_map_option = match (&map_assets_option, &tmx_maps_option) {
+        (Some(map_assets), Some(tmx_maps)) => tmx_maps.get(&map_assets.map),
+        _ => None,
+    };
+    let loaded_map_result = match tmx_map_option {
+        Some(tmx_map) => LoadedMap::from_tmx_map(tmx_map),
+        None => LoadedMap::read(&map_path.0),
+    };
+    let loaded_map = match loaded_map_result {
+        Ok(loaded_map) => loaded_map,
+        Err(error) => {
+            error!("{}", error);
+            commands.insert_resource(MapLoadFailed(error));
+            commands.insert_resource(NextState(config::EngineState::LoadingFailed));
+            return;
+        }
+    };
+    let mut game_setup = match game_setup_option {
+        Some(game_setup) => game_setup.clone(),
+        None => loaded_map.setup.clone().unwrap_or_default(),
+    };
+    if let Some(seed) = options_option.and_then(|options| options.seed) {
+        game_setup.seed = Some(seed);
+    }
+    let player_problems = loaded_map.player_problems(&game_setup);
+    if !player_problems.is_empty() {
+        let error = MapLoadError::Invalid {
+            path: tmx_map_option.map_or_else(|| map_path.0.clone(), |tmx_map| tmx_map.path.clone()),
+            problems: player_problems,
+        };
+        error!("{}", error);
+        commands.insert_resource(MapLoadFailed(error));
+        commands.insert_resource(NextState(config::EngineState::LoadingFailed));
+        return;
+    }
 
+    commands.insert_resource(game_setup.rules.clone());
+    let game_rng = game_setup.game_rng();
+    info!("Game seed {}", game_rng.seed());
+    commands.insert_resource(game_rng);
     let world_entity = world_query.single();
-    let player_entity = player_query.single();
-
-    commands
-        .entity(world_entity)
-        .insert(game::map::Map { width, height });
-
-    let mut province_polygons = Vec::new();
-    let province_layer = get_object_layer(&map, ObjectLayerName::Provinces);
-    for province in province_layer.objects() {
-        if let ObjectShape::Polygon { points } = &province.shape {
-            let province_polygon = ConvexPolygon::<f32, ()>::hull(
-                points
-                    .iter()
-                    .map(|(x, y)| point2(province.x + *x, province.y + *y))
-                    .collect(),
-            )
-            .unwrap();
-            let province_entity = commands
-                .spawn()
-                .insert(game::province::Province {
-                    name: province.id().to_string(),
-                })
-                .id();
-            commands.entity(world_entity).add_child(province_entity);
-            province_polygons.push((province_entity, province_polygon));
+    let player_entities = game_setup.spawn_players(&mut commands, &mut game_ids, world_entity);
+
+    let province_entities = loaded_map.spawn_terrain(&mut commands, world_entity);
+
+    // Provinces start owned by the owner of their first city
+    let mut province_owners = vec![None; province_entities.len()];
+    for city in loaded_map.cities.iter() {
+        let province_entity = province_entities[city.province];
+        let player_option = game_setup
+            .city_owner(&city.name, city.player.as_deref())
+            .map(|index| player_entities[index]);
+        let province_owner = &mut province_owners[city.province];
+        *province_owner = province_owner.or(player_option);
+        let city_entity = CityBundle::new_empty_city(
+            &mut commands.spawn(),
+            game_ids.next(),
+            player_option,
+            city.city_type.get_city_stats(),
+            province_entity,
+            city.position,
+        );
+        commands.entity(province_entity).add_child(city_entity);
+    }
+    // Provinces without owned cities can be given to a player by the map
+    for ((province, province_entity), owner_option) in loaded_map
+        .provinces
+        .iter()
+        .zip(province_entities.iter())
+        .zip(province_owners)
+    {
+        let owner_option = owner_option.or_else(|| {
+            game_setup
+                .named_owner(province.player.as_deref())
+                .map(|index| player_entities[index])
+        });
+        if let Some(owner) = owner_option {
+            commands.entity(*province_entity).insert(OfPlayer(owner));
         }
     }
 
-    for map_x in 0..width {
-        for map_y in 0..height {
-            let tile = base_layer.get_tile(map_x as i32, map_y as i32).unwrap();
-            let river = rivers_layer.get_tile(map_x as i32, map_y as i32);
-            let road = roads_layer.get_tile(map_x as i32, map_y as i32);
-            let forest_and_mountain =
-                forests_and_mountains_layer.get_tile(map_x as i32, map_y as i32);
-            let center_point = point2((map_x * 16) as f32 + 8., (map_y * 16) as f32 + 8.);
-            let province_option = province_polygons
+    for site in loaded_map.sites.iter() {
+        let province_entity = province_entities[site.province];
+        let player_option = game_setup
+            .named_owner(site.player.as_deref())
+            .map(|index| player_entities[index]);
+        let site_entity = SiteBundle::new_site(
+            &mut commands.spawn(),
+            game_ids.next(),
+            player_option,
+            site.site_type,
+            province_entity,
+            site.position,
+        );
+        commands.entity(province_entity).add_child(site_entity);
+    }
+
+    commands.insert_resource(NextState(config::EngineState::LoadingWorld.next()));
+}
+
+/// Map was rebuilt from changed file, graphics of it should be rebuilt too
+pub struct MapReloaded;
+
+/// Rebuild provinces and terrain when map file changes. Cities and sites stay
+/// as they are and are moved to the province they are in on the new map,
+/// ones added to the file only appear in new games.
+#[allow(clippy::too_many_arguments)]
+pub fn reload_map(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<TmxMap>>,
+    mut reloaded_events: EventWriter<MapReloaded>,
+    map_assets: Res<MapAssets>,
+    tmx_maps: Res<Assets<TmxMap>>,
+    world_query: Query<Entity, With<game::GameWorld>>,
+    player_query: Query<(Entity, &PlayerName), With<Player>>,
+    province_query: Query<(
+        Entity,
+        &Province,
+        Option<&OfPlayer>,
+        &ProvinceOccupation,
+        Option<&Children>,
+    )>,
+    city_query: Query<
+        (Entity, &GameId, &Position, Option<&City>, Option<&OfPlayer>),
+        Or<(With<City>, With<Site>)>,
+    >,
+) {
+    let map_changed = asset_events
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { handle } if *handle == map_assets.map));
+    if !map_changed {
+        return;
+    }
+    let loaded_map = match tmx_maps.get(&map_assets.map).map(LoadedMap::from_tmx_map) {
+        Some(Ok(loaded_map)) => loaded_map,
+        Some(Err(error)) => {
+            error!("Map wasn't reloaded, {}", error);
+            return;
+        }
+        None => return,
+    };
+
+    // Owner and occupation of provinces still in the map are kept, so
+    // reloading doesn't undo what happened in the game
+    let mut previous_provinces = HashMap::new();
+    for (province_entity, province, owner_option, occupation, children_option) in
+        province_query.iter()
+    {
+        previous_provinces.insert(
+            province.object_id,
+            (owner_option.map(|OfPlayer(owner)| *owner), *occupation),
+        );
+        if let Some(children) = children_option {
+            let cities = children
                 .iter()
-                .find(|(_, polygon)| polygon.contains_point(center_point));
-            if let Some((province_entity, _)) = province_option {
-                let x = map_x;
-                let y = height - map_y - 1;
-                let terrain_top = match (river, road, forest_and_mountain) {
-                    (Some(_), None, _) => TerrainTop::River,
-                    (None, Some(_), _) => TerrainTop::Road(RoadType::Path),
-                    (Some(_), Some(_), _) => TerrainTop::RiverWithBridge(RoadType::Path),
-                    (_, _, Some(tile)) if tile.id() >= 1248 => {
-                        TerrainTop::Mountain(MountainType::Rock)
+                .filter(|child| city_query.get(**child).is_ok())
+                .copied()
+                .collect::<Vec<_>>();
+            commands.entity(province_entity).remove_children(&cities);
+        }
+        commands.entity(province_entity).despawn_recursive();
+    }
+    let province_entities = loaded_map.spawn_terrain(&mut commands, world_query.single());
+    let province_at = loaded_map
+        .terrain
+        .iter()
+        .map(|tile| (tile.position, tile.province))
+        .collect::<HashMap<_, _>>();
+    // New provinces are owned like on load, by the owner of their first city
+    // or the player map gives them to
+    let mut province_owners: Vec<Option<(GameId, Entity)>> = vec![None; province_entities.len()];
+    for (city_entity, id, position, city_option, owner_option) in city_query.iter() {
+        match province_at.get(position) {
+            Some(province) => {
+                let province_entity = province_entities[*province];
+                if let (Some(_), Some(OfPlayer(owner))) = (city_option, owner_option) {
+                    let province_owner = &mut province_owners[*province];
+                    if province_owner.map_or(true, |(owner_id, _)| *id < owner_id) {
+                        *province_owner = Some((*id, *owner));
                     }
-                    (_, _, Some(_)) => TerrainTop::Forest(ForestType::Pine),
-                    _ => TerrainTop::None,
-                };
-                let terrain = commands
-                    .spawn_bundle(TerrainBundle {
-                        province: game::province::InProvince(*province_entity),
-                        position: Position::new(x, y),
-                        base: TerrainBase(TerrainType::from_u32(tile.id()).unwrap()),
-                        top: terrain_top,
-                        ..Default::default()
-                    })
-                    .id();
-                commands.entity(*province_entity).add_child(terrain);
-            } else {
-                panic!("NOT FOUND{:?}", (map_x, map_y, center_point, tile.id()));
+                }
+                commands
+                    .entity(city_entity)
+                    .insert(InProvince(province_entity));
+                commands.entity(province_entity).add_child(city_entity);
             }
+            None => warn!("City or site at {:?} is not on the reloaded map", position),
         }
     }
-
-    let cities_layer = get_object_layer(&map, ObjectLayerName::Cities);
-    for city in cities_layer.objects() {
-        let center_point = point2(city.x, city.y);
-        let province_option = province_polygons
-            .iter()
-            .find(|(_, polygon)| polygon.contains_point(center_point));
-        if let Some((province_entity, _)) = province_option {
-            let x = (city.x / 16.) as u32;
-            let y = height - (city.y / 16.) as u32 - 1;
-            if let Some(PropertyValue::StringValue(city_type_str)) =
-                city.properties.get("city_type")
-            {
-                let city_type = game::province::CityType::from_str(city_type_str).unwrap();
-                let city = CityBundle::new_empty_city(
-                    &mut commands.spawn(),
-                    player_entity,
-                    city_type.get_city_stats(),
-                    *province_entity,
-                    game::map::Position { x, y },
-                );
-                commands.entity(*province_entity).add_child(city);
+    for ((province, province_entity), owner_option) in loaded_map
+        .provinces
+        .iter()
+        .zip(province_entities.iter())
+        .zip(province_owners)
+    {
+        let owner_option = match previous_provinces.get(&province.object_id) {
+            Some((previous_owner, occupation)) => {
+                commands.entity(*province_entity).insert(*occupation);
+                *previous_owner
             }
+            None => owner_option.map(|(_, owner)| owner).or_else(|| {
+                player_query
+                    .iter()
+                    .find(|(_, PlayerName(name))| province.player.as_ref() == Some(name))
+                    .map(|(player, _)| player)
+            }),
+        };
+        if let Some(owner) = owner_option {
+            commands.entity(*province_entity).insert(OfPlayer(owner));
         }
     }
+    info!("Reloaded map");
+    reloaded_events.send(MapReloaded);
+}
 
-    commands.insert_resource(NextState(config::EngineState::LoadingWorld.next()));
+/// Game position of the tile an object is on, from its pixel coordinates in
+/// Tiled (y going down). None if the object is outside the map.
+fn object_position(x: f32, y: f32, width: u32, height: u32) -> Option<Position> {
+    if x < 0. || y < 0. {
+        return None;
+    }
+    let (tile_x, tile_y) = ((x / 16.) as u32, (y / 16.) as u32);
+    (tile_x < width && tile_y < height).then(|| Position::new(tile_x, height - tile_y - 1))
+}
+
+/// Name for provinces the map doesn't name, made from the object id so it
+/// stays the same when the map is reloaded
+fn generate_province_name(object_id: u32) -> String {
+    const PREFIXES: [&str; 16] = [
+        "Ash", "Bel", "Cor", "Dun", "Eld", "Fal", "Gar", "Hol", "Ist", "Kel", "Lor", "Mar", "Nor",
+        "Or", "Ros", "Tal",
+    ];
+    const SUFFIXES: [&str; 16] = [
+        "mere", "wood", "dale", "fen", "moor", "reach", "vale", "holm", "ford", "march", "crest",
+        "shire", "field", "gard", "haven", "wick",
+    ];
+    let index = object_id as usize;
+    let name = format!(
+        "{}{}",
+        PREFIXES[index % PREFIXES.len()],
+        SUFFIXES[(index / PREFIXES.len()) % SUFFIXES.len()]
+    );
+    let cycle = index / (PREFIXES.len() * SUFFIXES.len());
+    if cycle == 0 {
+        name
+    } else {
+        format!("{} {}", name, cycle + 1)
+    }
 }
 
-fn get_tile_layer(map: &Map, layer: TileLayerName) -> FiniteTileLayer {
+fn get_tile_layer(
+    map: &Map,
+    layer: TileLayerName,
+    problems: &mut Vec<MapProblem>,
+) -> Option<FiniteTileLayer> {
     match map
         .layers()
         .find(|found_layer| found_layer.name == layer.to_string())
-        .unwrap()
-        .layer_type()
+        .map(|found_layer| found_layer.layer_type())
     {
-        LayerType::TileLayer(TileLayer::Finite(layer)) => layer,
-        _ => panic!("Wrong layer type"),
+        Some(LayerType::TileLayer(TileLayer::Finite(found_layer))) => Some(found_layer),
+        Some(_) => {
+            problems.push(MapProblem::WrongLayerType {
+                layer: layer.to_string(),
+                expected: "finite tile",
+            });
+            None
+        }
+        None => {
+            problems.push(MapProblem::MissingLayer {
+                layer: layer.to_string(),
+            });
+            None
+        }
     }
 }
 
-fn get_object_layer(map: &Map, layer: ObjectLayerName) -> ObjectLayer {
+fn get_object_layer(
+    map: &Map,
+    layer: ObjectLayerName,
+    problems: &mut Vec<MapProblem>,
+) -> Option<ObjectLayer> {
     match map
         .layers()
         .find(|found_layer| found_layer.name == layer.to_string())
-        .unwrap()
-        .layer_type()
+        .map(|found_layer| found_layer.layer_type())
     {
-        LayerType::ObjectLayer(layer) => layer,
-        _ => panic!("Wrong layer type"),
+        Some(LayerType::ObjectLayer(found_layer)) => Some(found_layer),
+        Some(_) => {
+            problems.push(MapProblem::WrongLayerType {
+                layer: layer.to_string(),
+                expected: "object",
+            });
+            None
+        }
+        None => {
+            problems.push(MapProblem::MissingLayer {
+                layer: layer.to_string(),
+            });
+            None
+        }
+    }
+}
+
+fn polygon_problem(object_id: u32, error: PolygonError) -> MapProblem {
+    match error {
+        PolygonError::NoArea => MapProblem::InvalidProvincePolygon { object_id },
+        PolygonError::SelfIntersecting => MapProblem::SelfIntersectingProvince { object_id },
+    }
+}
+
+/// Value of a subtype property (eg `forest_type`) of the tile in its tileset.
+/// Error has property name and the value that isn't a known subtype, or no
+/// value when the tile needs the property but doesn't have it.
+fn tile_subtype<T: FromStr>(
+    tile: &LayerTile,
+    property: &'static str,
+) -> Result<Option<T>, (&'static str, Option<String>)> {
+    let tile_data = match tile.get_tile() {
+        Some(tile_data) => tile_data,
+        None => return Ok(None),
+    };
+    match tile_data.properties.get(property) {
+        Some(PropertyValue::StringValue(value)) => T::from_str(value)
+            .map(Some)
+            .map_err(|_| (property, Some(value.clone()))),
+        _ => Ok(None),
+    }
+}
+
+/// Tile is from the tileset of given image (eg `roads_and_rivers`), so its
+/// position in the image says its subtype
+fn is_from_tileset(tile: &LayerTile, name: &str) -> bool {
+    let tileset = tile.get_tileset();
+    tileset.name == name
+        || tileset.image.as_ref().map_or(false, |image| {
+            image.source.file_stem() == Some(std::ffi::OsStr::new(name))
+        })
+}
+
+/// `road_type` property of the tile, or road type by where tile is in
+/// `roads_and_rivers.png`: every road type is a block of 8 columns, Path and
+/// BrownCobblestone in rows 2-6, BlueCobblestone and Bricks below them.
+/// Bridges are in row 1, path bridge at 24 and 25 and cobblestone bridge,
+/// that renderer uses for every other road type, at 26 and 27.
+fn road_type(tile: &LayerTile) -> Result<RoadType, (&'static str, Option<String>)> {
+    if let Some(road_type) = tile_subtype(tile, "road_type")? {
+        return Ok(road_type);
+    }
+    if !is_from_tileset(tile, "roads_and_rivers") {
+        return Err(("road_type", None));
+    }
+    Ok(match tile.id() {
+        24 | 25 => RoadType::Path,
+        26 | 27 => RoadType::BrownCobblestone,
+        id => match (id / 16 >= 7, id % 16 >= 8) {
+            (false, false) => RoadType::Path,
+            (false, true) => RoadType::BrownCobblestone,
+            (true, false) => RoadType::BlueCobblestone,
+            (true, true) => RoadType::Bricks,
+        },
+    })
+}
+
+/// `mountain_type` or `forest_type` property of the tile, or subtype by
+/// where tile is in `forest_and_mountains.png`
+fn forest_or_mountain(tile: &LayerTile) -> Result<TerrainTop, (&'static str, Option<String>)> {
+    if let Some(mountain_type) = tile_subtype(tile, "mountain_type")? {
+        return Ok(TerrainTop::Mountain(mountain_type));
+    }
+    if let Some(forest_type) = tile_subtype(tile, "forest_type")? {
+        return Ok(TerrainTop::Forest(forest_type));
+    }
+    if !is_from_tileset(tile, "forest_and_mountains") {
+        return Err(("forest_type or mountain_type", None));
     }
+    forest_and_mountains_subtype(tile.id()).ok_or(("forest_type or mountain_type", None))
+}
+
+/// Subtype drawn by tile `id` of `forest_and_mountains.png`: forests are
+/// blocks of rows starting at Beech 156, Pine 312, Spruce 624 and Oak 1092,
+/// mountains start at 1248 and are blocks of 13 columns in rows 52 tiles
+/// wide. Tiles before the first forest aren't either.
+pub fn forest_and_mountains_subtype(id: u32) -> Option<TerrainTop> {
+    Some(match id {
+        id if id >= 1248 => TerrainTop::Mountain(match (id - 1248) % 52 / 13 {
+            0 => MountainType::Dirt,
+            1 => MountainType::Sand,
+            2 => MountainType::Rock,
+            _ => MountainType::RockIceCapped,
+        }),
+        id if id >= 1092 => TerrainTop::Forest(ForestType::Oak),
+        id if id >= 624 => TerrainTop::Forest(ForestType::Spruce),
+        id if id >= 312 => TerrainTop::Forest(ForestType::Pine),
+        id if id >= 156 => TerrainTop::Forest(ForestType::Beech),
+        _ => return None,
+    })
 }
 
 #[derive(Clone, Copy, Debug, EnumString, EnumIter, Display)]
@@ -1,4 +1,5 @@
 use num_derive::FromPrimitive;
+use serde::{Deserialize, Serialize};
 use strum_macros::{EnumIter, EnumString};
 
 use crate::prelude::*;
@@ -59,15 +60,10 @@ where
 {
     std::cmp::min(std::cmp::max(input, min), max)
 }
-#[derive(Component, Debug)]
-pub struct ProvinceBorder {
-    pub color: Color,
-}
-
 #[derive(Component, Debug, Default)]
 pub struct TerrainBase(pub TerrainType);
 
-#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub x: u32,
     pub y: u32,
@@ -89,6 +85,32 @@ impl Position {
         }
     }
 
+    /// Neighbouring position in given direction, None if it would be outside of map bounds
+    pub fn neighbor(&self, direction: &Direction, width: u32, height: u32) -> Option<Position> {
+        let (shift_x, shift_y) = match direction {
+            Direction::NorthWest => (-1, 1),
+            Direction::North => (0, 1),
+            Direction::NorthEast => (1, 1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, -1),
+            Direction::South => (0, -1),
+            Direction::SouthWest => (-1, -1),
+            Direction::West => (-1, 0),
+        };
+        let x = self.x as i64 + shift_x;
+        let y = self.y as i64 + shift_y;
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            None
+        } else {
+            Some(Position::new(x as u32, y as u32))
+        }
+    }
+
+    /// Number of 8-directional steps between positions
+    pub fn distance(&self, other: &Position) -> u32 {
+        std::cmp::max(self.x.abs_diff(other.x), self.y.abs_diff(other.y))
+    }
+
     pub fn move_to_direction(&mut self, direction: &Direction) {
         let x = self.x;
         let y = self.y;
@@ -144,6 +166,55 @@ impl Default for Position {
 #[derive(Component, Debug, Default)]
 pub struct Terrain {}
 
+/// Terrain of every tile of the map indexed by position, for systems that need
+/// to look at lots of tiles at once (eg pathfinding)
+#[derive(Component, Debug)]
+pub struct MapTerrain {
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<(TerrainType, TerrainTop)>>,
+}
+
+impl MapTerrain {
+    pub fn new(width: u32, height: u32) -> MapTerrain {
+        MapTerrain {
+            width,
+            height,
+            tiles: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        if position.x < self.width && position.y < self.height {
+            Some((position.y * self.width + position.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, position: &Position, base: TerrainType, top: TerrainTop) {
+        if let Some(index) = self.index(position) {
+            self.tiles[index] = Some((base, top));
+        }
+    }
+
+    pub fn get(&self, position: &Position) -> Option<(TerrainType, TerrainTop)> {
+        self.index(position).and_then(|index| self.tiles[index])
+    }
+
+    pub fn neighbor(&self, position: &Position, direction: &Direction) -> Option<Position> {
+        position.neighbor(direction, self.width, self.height)
+    }
+}
+
 #[derive(Bundle, Debug, Default)]
 pub struct TerrainBundle {
     pub terrain: Terrain,
@@ -153,7 +224,7 @@ pub struct TerrainBundle {
     pub top: TerrainTop,
 }
 
-#[derive(Component, Debug, Default, Copy, Clone)]
+#[derive(Component, Debug, Default, Copy, Clone, Serialize, Deserialize)]
 pub enum TerrainTop {
     #[default]
     None,
@@ -172,7 +243,7 @@ impl TerrainTop {
     }
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, EnumString)]
+#[derive(Debug, Copy, Clone, EnumIter, EnumString, Serialize, Deserialize)]
 pub enum RoadType {
     Path,
     BrownCobblestone,
@@ -180,7 +251,7 @@ pub enum RoadType {
     Bricks,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, EnumString)]
+#[derive(Debug, Copy, Clone, EnumIter, EnumString, Serialize, Deserialize)]
 pub enum ForestType {
     Beech,
     Pine,
@@ -188,7 +259,7 @@ pub enum ForestType {
     Oak,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, EnumString)]
+#[derive(Debug, Copy, Clone, EnumIter, EnumString, Serialize, Deserialize)]
 pub enum MountainType {
     Dirt,
     Sand,
@@ -199,7 +270,20 @@ pub enum MountainType {
 /// Terrain number indicates priority ordering when rendering (higher = higher priority)
 /// It is also a texture id for base land
 #[allow(dead_code)]
-#[derive(Clone, Debug, Default, Copy, PartialOrd, Ord, Eq, PartialEq, Hash, FromPrimitive)]
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Copy,
+    PartialOrd,
+    Ord,
+    Eq,
+    PartialEq,
+    Hash,
+    FromPrimitive,
+    Serialize,
+    Deserialize,
+)]
 pub enum TerrainType {
     #[default]
     Water = 0,
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+
+use euclid::point2;
+use strum::IntoEnumIterator;
+
+use crate::{
+    game::{
+        load_map::{LoadedMap, MapProblem},
+        map::{MapTerrain, Position, TerrainType},
+        movement::{MovementType, TerrainMovementClass},
+        navigation::movement_cost,
+        province_index::ProvinceIndex,
+        setup::GameSetup,
+    },
+    prelude::*,
+};
+
+/// Problems that don't stop the map from loading, but make the game broken
+/// on it. `LoadedMap::read` problems aren't repeated.
+pub fn check_map(map: &LoadedMap) -> Vec<MapProblem> {
+    let mut terrain = MapTerrain::new(map.width, map.height);
+    for tile in map.terrain.iter() {
+        terrain.set(&tile.position, tile.base, tile.top);
+    }
+
+    let mut problems = overlapping_provinces(map);
+    problems.extend(match &map.setup {
+        Some(setup) => map.player_problems(setup),
+        None => map.player_problems(&GameSetup::default()),
+    });
+    problems.extend(misplaced_cities(map, &terrain));
+    problems.extend(unreachable_land(&terrain));
+    problems
+}
+
+/// Tiles in Tiled have y going down
+fn tiled_y(map: &LoadedMap, position: &Position) -> u32 {
+    map.height - position.y - 1
+}
+
+fn overlapping_provinces(map: &LoadedMap) -> Vec<MapProblem> {
+    let province_index = ProvinceIndex::new(&map.provinces, map.width, map.height);
+    let mut problems = Vec::new();
+    for tile in map.terrain.iter() {
+        let x = tile.position.x;
+        let y = tiled_y(map, &tile.position);
+        let center_point = point2((x * 16) as f32 + 8., (y * 16) as f32 + 8.);
+        let object_ids = province_index
+            .provinces_at(&map.provinces, center_point)
+            .map(|index| map.provinces[index].object_id)
+            .collect::<Vec<_>>();
+        if object_ids.len() > 1 {
+            problems.push(MapProblem::OverlappingProvinces { x, y, object_ids });
+        }
+    }
+    problems
+}
+
+fn misplaced_cities(map: &LoadedMap, terrain: &MapTerrain) -> Vec<MapProblem> {
+    let mut problems = Vec::new();
+    for city in map.cities.iter() {
+        let (width, height) = city.city_type.get_city_stats().size;
+        if city.position.x + width as u32 > map.width
+            || city.position.y + height as u32 > map.height
+        {
+            problems.push(MapProblem::CityOutsideMap {
+                object_id: city.object_id,
+                name: city.name.clone(),
+            });
+            continue;
+        }
+        let water_position = (0..width)
+            .flat_map(|x| (0..height).map(move |y| city.position.shift(x as u32, y as u32)))
+            .find(|position| match terrain.get(position) {
+                Some((base, _)) => is_water(base),
+                None => false,
+            });
+        if let Some(position) = water_position {
+            problems.push(MapProblem::CityOnWater {
+                object_id: city.object_id,
+                name: city.name.clone(),
+                x: position.x,
+                y: tiled_y(map, &position),
+            });
+        }
+    }
+    problems
+}
+
+fn is_water(base: TerrainType) -> bool {
+    TerrainMovementClass::from(base) == TerrainMovementClass::Water
+}
+
+/// Land is every tile that some unit can enter. Every region of land apart
+/// from the biggest one is reported.
+fn unreachable_land(terrain: &MapTerrain) -> Vec<MapProblem> {
+    let rules = MovementType::iter()
+        .map(|movement_type| movement_type.get_movement_rules())
+        .collect::<Vec<_>>();
+    let is_land = |position: &Position| {
+        rules
+            .iter()
+            .any(|rules| movement_cost(terrain, rules, position).is_some())
+    };
+
+    let width = terrain.width();
+    let mut visited = vec![false; (width * terrain.height()) as usize];
+    let mut regions: Vec<(Position, usize)> = Vec::new();
+    for y in 0..terrain.height() {
+        for x in 0..width {
+            let start = Position::new(x, y);
+            if visited[(y * width + x) as usize] || !is_land(&start) {
+                continue;
+            }
+            visited[(y * width + x) as usize] = true;
+            let mut tiles = 0;
+            let mut queue = VecDeque::from([start]);
+            while let Some(position) = queue.pop_front() {
+                tiles += 1;
+                for direction in Direction::iter() {
+                    if let Some(next) = terrain.neighbor(&position, &direction) {
+                        let index = (next.y * width + next.x) as usize;
+                        if !visited[index] && is_land(&next) {
+                            visited[index] = true;
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+            regions.push((start, tiles));
+        }
+    }
+
+    let biggest = regions
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (_, tiles))| *tiles)
+        .map(|(index, _)| index);
+    regions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| Some(*index) != biggest)
+        .map(|(_, (position, tiles))| MapProblem::UnreachableLand {
+            tiles,
+            x: position.x,
+            y: terrain.height() - position.y - 1,
+        })
+        .collect()
+}
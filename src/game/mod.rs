@@ -1,25 +1,60 @@
 use std::{collections::HashMap, time::Duration};
 
+use bevy::ecs::schedule::ShouldRun;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use thiserror::Error;
 
 pub mod actions;
+pub mod capacity;
+pub mod checksum;
+pub mod combat;
 pub mod load_map;
 pub mod map;
+pub mod map_check;
+pub mod movement;
+pub mod navigation;
+pub mod player_commands;
+pub mod polygon;
 pub mod province;
+pub mod province_graph;
+pub mod province_index;
+pub mod random;
+pub mod recruitment;
+pub mod replay;
+pub mod save;
+pub mod setup;
+pub mod site;
+pub mod start;
 pub mod units;
 pub mod world;
 
-use crate::{
-    game::{map::Position, units::unit_orders},
-    prelude::*,
-};
+use crate::{game::units::unit_orders, options::GameOptions, prelude::*};
 
 pub struct GamePlugin {}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         // Stage that will tick time
-        let mut game_tick_stage = SystemStage::parallel();
+        let mut game_tick_stage = SystemStage::parallel().with_run_criteria(game_tick_run_criteria);
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::Commands)
+                .label_and_after(config::CommandsLabel::Apply)
+                .run_in_state(InGameState::Running)
+                .with_system(player_commands::apply_player_commands)
+                .into(),
+        );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::Commands)
+                .label_and_after(config::CommandsLabel::Recruit)
+                .run_in_state(InGameState::Running)
+                .with_system(recruitment::recruit_units)
+                .into(),
+        );
         game_tick_stage.add_system_set(
             ConditionSet::new()
                 .label_and_after(config::GameTickStageLabel::Tick)
@@ -30,26 +65,109 @@ impl Plugin for GamePlugin {
         game_tick_stage.add_system_set(
             ConditionSet::new()
                 .label_and_after(config::GameTickStageLabel::UpdateEntities)
+                .label_and_after(config::UpdateEntitiesLabel::Movement)
                 .run_in_state(InGameState::Running)
                 .with_system(unit_orders)
                 .into(),
         );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::UpdateEntities)
+                .label_and_after(config::UpdateEntitiesLabel::Combat)
+                .run_in_state(InGameState::Running)
+                .with_system(combat::unit_combat)
+                .into(),
+        );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::UpdateEntities)
+                .label_and_after(config::UpdateEntitiesLabel::Recruitment)
+                .run_in_state(InGameState::Running)
+                .with_system(recruitment::progress_recruitment)
+                .into(),
+        );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::UpdateEntities)
+                .label_and_after(config::UpdateEntitiesLabel::Control)
+                .run_in_state(InGameState::Running)
+                .with_system(province::update_province_owners)
+                .into(),
+        );
         game_tick_stage.add_system_set(
             ConditionSet::new()
                 .label_and_after(config::GameTickStageLabel::UpdateResources)
+                .label_and_after(config::UpdateResourcesLabel::Capacity)
+                .run_in_state(InGameState::Running)
+                .with_system(capacity::update_capacity_resources)
+                .into(),
+        );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::UpdateResources)
+                .label_and_after(config::UpdateResourcesLabel::Upkeep)
                 .run_in_state(InGameState::Running)
                 .with_system(update_stockpile_resources)
+                .with_system(capacity::disband_over_capacity_units)
+                .into(),
+        );
+        game_tick_stage.add_system_set(
+            ConditionSet::new()
+                .label_and_after(config::GameTickStageLabel::Checksum)
+                .run_in_state(InGameState::Running)
+                .with_system(checksum::update_state_checksum)
                 .into(),
         );
 
-        app.add_enter_system(config::EngineState::LoadingWorld, setup_game_world)
+        app.init_resource::<GameTickTimer>()
+            .init_resource::<GameSpeed>()
+            .init_resource::<GameRules>()
+            .init_resource::<random::GameRng>()
+            .init_resource::<world::GameIds>()
+            .init_resource::<checksum::StateChecksum>()
+            .init_resource::<player_commands::PlayerCommandQueue>()
+            .add_event::<combat::BattleEvent>()
+            .add_event::<recruitment::RecruitUnit>()
+            .add_event::<capacity::UnitDisbanded>()
+            .add_event::<province::ProvinceOwnerChanged>()
+            .init_resource::<load_map::MapPath>()
+            .add_enter_system(
+                config::EngineState::LoadingWorld,
+                setup_game_world.run_unless_resource_exists::<save::LoadGame>(),
+            )
+            .add_enter_system(
+                config::EngineState::LoadingWorld,
+                save::load_game.run_if_resource_exists::<save::LoadGame>(),
+            )
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(config::EngineState::LoadingWorld)
+                    .run_unless_resource_exists::<save::LoadGame>()
                     .with_system(load_map::load_map)
                     .into(),
             )
             .add_exit_system(config::EngineState::LoadingWorld, setup_actions)
+            .add_event::<load_map::MapReloaded>()
+            .add_system_set(
+                ConditionSet::new()
+                    .label_and_after(config::UpdateStageLabel::GameActions)
+                    .run_in_state(config::EngineState::InGame)
+                    .run_if_resource_exists::<assets::MapAssets>()
+                    .run_if(is_dev_mode)
+                    .with_system(load_map::reload_map)
+                    .into(),
+            )
+            .add_exit_system(config::EngineState::LoadingWorld, start::view_as_player)
+            .add_enter_system(config::EngineState::InGame, start::start_game)
+            // Capacity is otherwise only known after the first tick
+            .add_enter_system(
+                config::EngineState::InGame,
+                capacity::update_capacity_resources,
+            )
+            .add_enter_system(
+                config::EngineState::InGame,
+                replay::start_recording.run_if_resource_exists::<replay::RecordReplay>(),
+            )
             .add_loopless_state(InGameState::Paused)
             .add_plugin(InputManagerPlugin::<actions::WorldActions>::default())
             .add_system_set(
@@ -59,75 +177,34 @@ impl Plugin for GamePlugin {
                     .with_system(handle_world_actions)
                     .into(),
             )
-            .add_stage_after(
-                CoreStage::Update,
-                config::Stage::GameTick,
-                FixedTimestepStage::new(Duration::from_millis(1000)).with_stage(game_tick_stage),
-            );
+            .add_system_set(
+                ConditionSet::new()
+                    .label_and_after(config::UpdateStageLabel::GameActions)
+                    .run_in_state(config::EngineState::InGame)
+                    .run_if_resource_exists::<start::SkipToDay>()
+                    .with_system(start::skip_to_day)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .label_and_after(config::UpdateStageLabel::UpdateCache)
+                    .run_in_state(config::EngineState::InGame)
+                    .with_system(world::update_player_ledgers)
+                    .into(),
+            )
+            .add_stage_after(CoreStage::Update, config::Stage::GameTick, game_tick_stage);
     }
 }
 
+fn is_dev_mode(options_option: Option<Res<GameOptions>>) -> bool {
+    options_option.map_or(false, |options| options.dev)
+}
+
+/// Players are spawned together with the map, as game setup can come from it
 fn setup_game_world(mut commands: Commands) {
     commands
         .spawn_bundle(GameWorldBundle::empty())
-        .insert(FirstDay(true))
-        .with_children(|builder| {
-            builder
-                .spawn_bundle(world::PlayerBundle {
-                    color: world::PlayerColor(Color::RED),
-                    name: world::PlayerName("PLAYER".to_string()),
-                    ..Default::default()
-                })
-                .with_children(|builder| {
-                    let player_entity = builder.parent_entity();
-                    builder.spawn_bundle(game::world::PlayerStockpileBundle {
-                        player: game::world::OfPlayer(player_entity),
-                        resource: game::world::StockpileResourceType::Gold,
-                        amount: game::world::StockpileResourceAmount(100.),
-                    });
-                    builder.spawn_bundle(game::world::PlayerStockpileBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::StockpileResourceType::Wood,
-                        amount: game::world::StockpileResourceAmount(50.),
-                    });
-                    builder.spawn_bundle(game::world::PlayerCapacityBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::CapacityResourceType::Sun,
-                    });
-                    builder.spawn_bundle(game::world::PlayerCapacityBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::CapacityResourceType::Arcana,
-                    });
-                    builder.spawn_bundle(game::world::PlayerCapacityBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::CapacityResourceType::Death,
-                    });
-                    builder.spawn_bundle(game::world::PlayerCapacityBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::CapacityResourceType::Chaos,
-                    });
-                    builder.spawn_bundle(game::world::PlayerCapacityBundle {
-                        player: game::world::OfPlayer(player_entity),
-
-                        resource: game::world::CapacityResourceType::Nature,
-                    });
-                    let mut unit = builder.spawn();
-                    game::units::UnitBundle::insert_full(
-                        &mut unit,
-                        player_entity,
-                        units::UnitType::Skeleton,
-                        Position { x: 64, y: 40 },
-                    );
-                })
-                .insert_bundle(ui::ViewerBundle {
-                    ..Default::default()
-                });
-        });
+        .insert(FirstDay(true));
 }
 
 fn setup_actions(mut commands: Commands, world_query: Query<Entity, With<GameWorld>>) {
@@ -144,10 +221,20 @@ fn setup_actions(mut commands: Commands, world_query: Query<Entity, With<GameWor
         });
 }
 
-fn game_tick(mut game_time_query: Query<(&mut GameTick, &mut GameDay, &mut FirstDay)>) {
+fn game_tick_run_criteria(
+    time: Res<Time>,
+    mut game_tick_timer: ResMut<GameTickTimer>,
+) -> ShouldRun {
+    game_tick_timer.should_tick(time.delta())
+}
+
+fn game_tick(
+    game_rules: Res<GameRules>,
+    mut game_time_query: Query<(&mut GameTick, &mut GameDay, &mut FirstDay)>,
+) {
     let (mut game_tick, mut game_day, mut first_day) = game_time_query.single_mut();
     game_tick.0 += 1;
-    if game_tick.0 >= 10 {
+    if game_tick.0 >= game_rules.ticks_per_day {
         game_tick.0 = 0;
         game_day.0 += 1;
         first_day.0 = false;
@@ -155,7 +242,6 @@ fn game_tick(mut game_time_query: Query<(&mut GameTick, &mut GameDay, &mut First
 }
 
 fn update_stockpile_resources(
-    mut _commands: Commands,
     game_tick_query: Query<(&GameTick, &FirstDay), Changed<GameDay>>,
     mut stockpiles_query: Query<(
         &game::world::OfPlayer,
@@ -166,42 +252,46 @@ fn update_stockpile_resources(
         &game::world::OfPlayer,
         &game::world::StockpileResourceType,
         &game::world::StockpileResourceProsumer,
+        Option<&Parent>,
     )>,
+    id_query: Query<&game::world::GameId>,
 ) {
     if let Ok((game_tick, first_day)) = game_tick_query.get_single() {
         if game_tick.0 == 0 && !first_day.0 {
-            let mut stockpiles_by_player: HashMap<
-                u32,
-                HashMap<
-                    game::world::StockpileResourceType,
-                    Mut<game::world::StockpileResourceAmount>,
-                >,
-            > = HashMap::new();
-            for (player, stockpile_resource_type, stockpile_resource_amount) in
-                stockpiles_query.iter_mut()
-            {
-                let stockpile_amount = stockpile_resource_amount;
-                #[allow(unused_must_use)]
-                {
-                    stockpiles_by_player.try_insert(player.0.id(), HashMap::new());
-                }
-                stockpiles_by_player.entry(player.0.id()).and_modify(
-                    |m: &mut HashMap<
-                        game::world::StockpileResourceType,
-                        Mut<game::world::StockpileResourceAmount>,
-                    >| {
-                        m.insert(*stockpile_resource_type, stockpile_amount);
+            // Floats are added up in source id order, so that the sums come
+            // out the same however the entities are stored
+            let mut prosumers = prosumers_query
+                .iter()
+                .map(
+                    |(
+                        game::world::OfPlayer(player),
+                        resource,
+                        game::world::StockpileResourceProsumer(amount),
+                        parent_option,
+                    )| {
+                        let source_id = parent_option
+                            .and_then(|Parent(parent)| id_query.get(*parent).ok())
+                            .copied();
+                        (source_id, *resource, *amount, *player)
                     },
-                );
+                )
+                .collect::<Vec<_>>();
+            prosumers.sort_by(|a, b| {
+                (a.0, a.1)
+                    .cmp(&(b.0, b.1))
+                    .then_with(|| a.2.total_cmp(&b.2))
+            });
+            let mut incomes: HashMap<(Entity, game::world::StockpileResourceType), f32> =
+                HashMap::new();
+            for (_, resource, amount, player) in prosumers {
+                *incomes.entry((player, resource)).or_default() += amount;
             }
 
-            for (player, stockpile_resource_type, game::world::StockpileResourceProsumer(amount)) in
-                prosumers_query.iter()
+            for (game::world::OfPlayer(player), resource, mut stockpile_amount) in
+                stockpiles_query.iter_mut()
             {
-                if let Some(by_type) = stockpiles_by_player.get_mut(&player.0.id()) {
-                    if let Some(stockpile_amount) = by_type.get_mut(stockpile_resource_type) {
-                        stockpile_amount.0 += amount;
-                    }
+                if let Some(income) = incomes.get(&(*player, *resource)) {
+                    stockpile_amount.0 += income;
                 }
             }
         }
@@ -210,9 +300,17 @@ fn update_stockpile_resources(
 
 fn handle_world_actions(
     mut commands: Commands,
+    mut game_speed: ResMut<GameSpeed>,
+    mut game_tick_timer: ResMut<GameTickTimer>,
     action_state_query: Query<&ActionState<actions::WorldActions>>,
 ) {
     let action_state = action_state_query.single();
+    for speed in GameSpeed::iter() {
+        if action_state.just_pressed(speed.world_action()) && *game_speed != speed {
+            *game_speed = speed;
+            game_tick_timer.set_rate(speed.tick_rate());
+        }
+    }
     if action_state.just_pressed(actions::WorldActions::Pause) {
         commands.insert_resource(NextState(InGameState::Paused));
     }
@@ -220,6 +318,10 @@ fn handle_world_actions(
     if action_state.just_pressed(actions::WorldActions::Resume) {
         commands.insert_resource(NextState(InGameState::Running));
     }
+
+    if action_state.just_pressed(actions::WorldActions::Save) {
+        save::save_game(&mut commands, save::QUICKSAVE_PATH.into());
+    }
 }
 
 #[derive(Component, Debug)]
@@ -255,6 +357,170 @@ pub struct GameTick(pub usize);
 #[derive(Component, Debug, PartialEq, Eq)]
 pub struct GameDay(pub u32);
 
+/// How often `config::Stage::GameTick` runs
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TickRate {
+    /// Tick every time this much real time has passed
+    Every(Duration),
+    /// Tick once per app update, regardless of real time. Used to run the
+    /// simulation as fast as possible or to step it manually.
+    EveryUpdate,
+}
+
+#[derive(Debug)]
+pub struct GameTickTimer {
+    pub rate: TickRate,
+    accumulator: Duration,
+    looping: bool,
+}
+
+impl GameTickTimer {
+    pub fn new(rate: TickRate) -> GameTickTimer {
+        GameTickTimer {
+            rate,
+            accumulator: Duration::ZERO,
+            looping: false,
+        }
+    }
+
+    pub fn set_rate(&mut self, rate: TickRate) {
+        self.rate = rate;
+        self.accumulator = Duration::ZERO;
+        self.looping = false;
+    }
+
+    fn should_tick(&mut self, delta: Duration) -> ShouldRun {
+        match self.rate {
+            TickRate::EveryUpdate => ShouldRun::Yes,
+            TickRate::Every(step) => {
+                // Criteria is re-checked after every tick, only accumulate time once per frame
+                if !self.looping {
+                    self.accumulator += delta;
+                }
+                if self.accumulator >= step {
+                    self.accumulator -= step;
+                    self.looping = true;
+                    ShouldRun::YesAndCheckAgain
+                } else {
+                    self.looping = false;
+                    ShouldRun::No
+                }
+            }
+        }
+    }
+}
+
+impl Default for GameTickTimer {
+    fn default() -> Self {
+        GameTickTimer::new(GameSpeed::default().tick_rate())
+    }
+}
+
+/// Real time length of a tick at normal speed
+const NORMAL_TICK_DURATION: Duration = Duration::from_millis(1000);
+
+/// Speed the game runs at when it's not paused, changed with speed
+/// `WorldActions`
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default, EnumIter)]
+pub enum GameSpeed {
+    Half,
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+    // Tick every frame
+    Max,
+}
+
+impl GameSpeed {
+    pub fn tick_rate(&self) -> TickRate {
+        match self {
+            GameSpeed::Half => TickRate::Every(NORMAL_TICK_DURATION * 2),
+            GameSpeed::Normal => TickRate::Every(NORMAL_TICK_DURATION),
+            GameSpeed::Double => TickRate::Every(NORMAL_TICK_DURATION / 2),
+            GameSpeed::Quadruple => TickRate::Every(NORMAL_TICK_DURATION / 4),
+            GameSpeed::Max => TickRate::EveryUpdate,
+        }
+    }
+
+    pub fn world_action(&self) -> actions::WorldActions {
+        match self {
+            GameSpeed::Half => actions::WorldActions::SpeedHalf,
+            GameSpeed::Normal => actions::WorldActions::SpeedNormal,
+            GameSpeed::Double => actions::WorldActions::SpeedDouble,
+            GameSpeed::Quadruple => actions::WorldActions::SpeedQuadruple,
+            GameSpeed::Max => actions::WorldActions::SpeedMax,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameSpeed::Half => "0.5x",
+            GameSpeed::Normal => "1x",
+            GameSpeed::Double => "2x",
+            GameSpeed::Quadruple => "4x",
+            GameSpeed::Max => "Max",
+        }
+    }
+
+    pub fn faster(&self) -> GameSpeed {
+        match self {
+            GameSpeed::Half => GameSpeed::Normal,
+            GameSpeed::Normal => GameSpeed::Double,
+            GameSpeed::Double => GameSpeed::Quadruple,
+            GameSpeed::Quadruple | GameSpeed::Max => GameSpeed::Max,
+        }
+    }
+
+    pub fn slower(&self) -> GameSpeed {
+        match self {
+            GameSpeed::Half | GameSpeed::Normal => GameSpeed::Half,
+            GameSpeed::Double => GameSpeed::Normal,
+            GameSpeed::Quadruple => GameSpeed::Double,
+            GameSpeed::Max => GameSpeed::Quadruple,
+        }
+    }
+}
+
+/// Rules of the game that can differ between games, come from game setup
+/// and are saved with the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    pub ticks_per_day: usize,
+    // Ticks units have to hold a province alone to take it
+    pub province_occupation_ticks: u32,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            ticks_per_day: 10,
+            province_occupation_ticks: 20,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GameRulesError {
+    #[error("game rule {0} has to be at least 1")]
+    Zero(&'static str),
+}
+
+impl GameRules {
+    /// Rules from setup files and saves are checked before the game starts,
+    /// as eg zero ticks per day would break the day counter
+    pub fn validate(&self) -> Result<(), GameRulesError> {
+        if self.ticks_per_day == 0 {
+            return Err(GameRulesError::Zero("ticks_per_day"));
+        }
+        if self.province_occupation_ticks == 0 {
+            return Err(GameRulesError::Zero("province_occupation_ticks"));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
 pub enum InGameState {
     #[default]
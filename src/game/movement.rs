@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use strum_macros::{EnumIter, EnumString};
+
+use crate::{
+    game::map::{TerrainTop, TerrainType},
+    prelude::*,
+};
+
+/// Progress unit needs to accumulate to move to next tile
+pub const MOVE_PROGRESS: u32 = 100;
+
+/// How unit moves across terrain, determines which rules table applies to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, EnumString)]
+pub enum MovementType {
+    Foot,
+    Mounted,
+    Climbing,
+}
+
+/// Groups of base terrain that share movement rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, EnumString)]
+pub enum TerrainMovementClass {
+    Open,
+    Rough,
+    Swamp,
+    Water,
+    Lava,
+}
+
+impl From<TerrainType> for TerrainMovementClass {
+    fn from(terrain_type: TerrainType) -> Self {
+        match terrain_type {
+            TerrainType::Water | TerrainType::WaterOcean => TerrainMovementClass::Water,
+            TerrainType::Lava | TerrainType::LavaCracks => TerrainMovementClass::Lava,
+            TerrainType::WaterSwamp
+            | TerrainType::Swamp
+            | TerrainType::SwampBog
+            | TerrainType::SwampReeds => TerrainMovementClass::Swamp,
+            TerrainType::Ice
+            | TerrainType::Snow
+            | TerrainType::SnowDune
+            | TerrainType::SnowBlue
+            | TerrainType::SnowBlueDune
+            | TerrainType::Desert
+            | TerrainType::DesertDune
+            | TerrainType::DesertRed
+            | TerrainType::DesertRedCracked
+            | TerrainType::DesertYellow
+            | TerrainType::DesertYellowCracked => TerrainMovementClass::Rough,
+            TerrainType::Dirt
+            | TerrainType::DirtGrass
+            | TerrainType::GrassLand
+            | TerrainType::GrassLandPasture => TerrainMovementClass::Open,
+        }
+    }
+}
+
+/// Movement costs are in ticks needed to cross a tile. Extra costs for
+/// terrain tops are added to base terrain cost, None means tile with that
+/// terrain can't be entered.
+#[derive(Debug)]
+pub struct MovementRules {
+    // Base terrain that's missing can't be entered
+    pub terrain_cost: HashMap<TerrainMovementClass, u32>,
+    // Roads and bridges replace base terrain cost
+    pub road_cost: u32,
+    pub bridge_cost: u32,
+    pub river_crossing_cost: Option<u32>,
+    pub forest_cost: Option<u32>,
+    pub mountain_cost: Option<u32>,
+    pub cliff_cost: Option<u32>,
+}
+
+impl MovementType {
+    pub fn get_movement_rules(&self) -> MovementRules {
+        match self {
+            MovementType::Foot => MovementRules {
+                terrain_cost: HashMap::from([
+                    (TerrainMovementClass::Open, 4),
+                    (TerrainMovementClass::Rough, 6),
+                    (TerrainMovementClass::Swamp, 8),
+                ]),
+                road_cost: 2,
+                bridge_cost: 2,
+                river_crossing_cost: Some(4),
+                forest_cost: Some(2),
+                mountain_cost: Some(6),
+                cliff_cost: None,
+            },
+            MovementType::Mounted => MovementRules {
+                terrain_cost: HashMap::from([
+                    (TerrainMovementClass::Open, 3),
+                    (TerrainMovementClass::Rough, 5),
+                    (TerrainMovementClass::Swamp, 12),
+                ]),
+                road_cost: 1,
+                bridge_cost: 2,
+                river_crossing_cost: Some(6),
+                forest_cost: Some(4),
+                mountain_cost: None,
+                cliff_cost: None,
+            },
+            MovementType::Climbing => MovementRules {
+                terrain_cost: HashMap::from([
+                    (TerrainMovementClass::Open, 4),
+                    (TerrainMovementClass::Rough, 4),
+                    (TerrainMovementClass::Swamp, 6),
+                ]),
+                road_cost: 3,
+                bridge_cost: 3,
+                river_crossing_cost: Some(6),
+                forest_cost: Some(0),
+                mountain_cost: Some(2),
+                cliff_cost: Some(4),
+            },
+        }
+    }
+}
+
+impl MovementRules {
+    /// Ticks needed to cross a tile, None if it can't be entered
+    pub fn tile_cost(&self, base: TerrainType, top: TerrainTop) -> Option<u32> {
+        match top {
+            TerrainTop::Road(_) => Some(std::cmp::max(self.road_cost, 1)),
+            TerrainTop::RiverWithBridge(_) => Some(std::cmp::max(self.bridge_cost, 1)),
+            _ => {
+                let base_cost = *self.terrain_cost.get(&TerrainMovementClass::from(base))?;
+                let top_cost = match top {
+                    TerrainTop::River => self.river_crossing_cost?,
+                    TerrainTop::Forest(_) => self.forest_cost?,
+                    TerrainTop::Mountain(_) => self.mountain_cost?,
+                    TerrainTop::Cliff => self.cliff_cost?,
+                    _ => 0,
+                };
+                Some(std::cmp::max(base_cost + top_cost, 1))
+            }
+        }
+    }
+
+    /// Move progress per tick when moving onto a tile, None if it can't be entered
+    pub fn progress_per_tick(&self, base: TerrainType, top: TerrainTop) -> Option<u32> {
+        self.tile_cost(base, top)
+            .map(|cost| (MOVE_PROGRESS + cost - 1) / cost)
+    }
+
+    /// Cheapest cost of crossing any tile
+    pub fn min_tile_cost(&self) -> u32 {
+        self.terrain_cost
+            .values()
+            .copied()
+            .chain([self.road_cost, self.bridge_cost])
+            .min()
+            .map_or(1, |cost| std::cmp::max(cost, 1))
+    }
+}
@@ -0,0 +1,91 @@
+use pathfinding::prelude::astar;
+use strum::IntoEnumIterator;
+
+use crate::{
+    game::{
+        map::{MapTerrain, Position},
+        movement::MovementRules,
+    },
+    prelude::*,
+};
+
+/// Cost of entering tile at position, None if it can't be entered or is outside of map
+pub fn movement_cost(
+    terrain: &MapTerrain,
+    rules: &MovementRules,
+    position: &Position,
+) -> Option<u32> {
+    terrain
+        .get(position)
+        .and_then(|(base, top)| rules.tile_cost(base, top))
+}
+
+/// Can unit at position step to next position in one move
+pub fn can_step(
+    terrain: &MapTerrain,
+    rules: &MovementRules,
+    position: &Position,
+    next_position: &Position,
+) -> bool {
+    position.distance(next_position) == 1
+        && successors(terrain, rules, position)
+            .iter()
+            .any(|(successor, _)| successor == next_position)
+}
+
+/// Find cheapest path between positions. Resulting path doesn't include the
+/// starting position, None if target can't be reached.
+pub fn find_path(
+    terrain: &MapTerrain,
+    rules: &MovementRules,
+    from_position: &Position,
+    to_position: &Position,
+) -> Option<Vec<Position>> {
+    movement_cost(terrain, rules, to_position)?;
+    let min_tile_cost = rules.min_tile_cost();
+    astar(
+        from_position,
+        |position| successors(terrain, rules, position),
+        |position| position.distance(to_position) * min_tile_cost,
+        |position| position == to_position,
+    )
+    .map(|(path, _)| path.into_iter().skip(1).collect())
+}
+
+fn successors(
+    terrain: &MapTerrain,
+    rules: &MovementRules,
+    position: &Position,
+) -> Vec<(Position, u32)> {
+    Direction::iter()
+        .filter_map(|direction| {
+            let next_position = terrain.neighbor(position, &direction)?;
+            let cost = movement_cost(terrain, rules, &next_position)?;
+            // Don't squeeze diagonally between two tiles that can't be entered
+            if let Some((side, other_side)) = diagonal_sides(&direction) {
+                let side_passable = terrain
+                    .neighbor(position, &side)
+                    .and_then(|side_position| movement_cost(terrain, rules, &side_position))
+                    .is_some();
+                let other_side_passable = terrain
+                    .neighbor(position, &other_side)
+                    .and_then(|side_position| movement_cost(terrain, rules, &side_position))
+                    .is_some();
+                if !side_passable && !other_side_passable {
+                    return None;
+                }
+            }
+            Some((next_position, cost))
+        })
+        .collect()
+}
+
+fn diagonal_sides(direction: &Direction) -> Option<(Direction, Direction)> {
+    match direction {
+        Direction::NorthEast => Some((Direction::North, Direction::East)),
+        Direction::SouthEast => Some((Direction::South, Direction::East)),
+        Direction::SouthWest => Some((Direction::South, Direction::West)),
+        Direction::NorthWest => Some((Direction::North, Direction::West)),
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        map::Position,
+        province::City,
+        recruitment::RecruitUnit,
+        replay::CommandRecorder,
+        units::{Unit, UnitOrder, UnitOrders, UnitType},
+        world::{GameId, OfPlayer, Player},
+        GameDay, GameTick,
+    },
+    prelude::*,
+};
+
+/// Something a player wants to happen in the game. Everything that changes
+/// the game on behalf of a player goes through commands, so that games can
+/// be recorded and replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameCommand {
+    MoveUnit {
+        unit: GameId,
+        target: Position,
+    },
+    RecruitUnit {
+        city: GameId,
+        unit_type: UnitType,
+        rally_point: Option<Position>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerCommand {
+    pub player: GameId,
+    pub command: GameCommand,
+}
+
+/// Command together with the tick it was applied at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    pub day: u32,
+    pub tick: usize,
+    pub command: PlayerCommand,
+}
+
+/// Commands given since the last tick, they are applied when next tick starts
+#[derive(Debug, Default)]
+pub struct PlayerCommandQueue {
+    pending: Vec<PlayerCommand>,
+}
+
+impl PlayerCommandQueue {
+    pub fn push(&mut self, player: GameId, command: GameCommand) {
+        self.pending.push(PlayerCommand { player, command });
+    }
+}
+
+/// Commands of a recorded game. When this resource exists, commands are
+/// only taken from it and the ones players give are ignored.
+#[derive(Debug, Default)]
+pub struct ReplayCommands {
+    pub commands: VecDeque<RecordedCommand>,
+}
+
+type CommandUnitQuery = (&'static GameId, &'static OfPlayer, &'static mut UnitOrders);
+
+/// Take commands that should be applied this tick, record them and apply
+/// them. Commands that refer to entities player doesn't own are dropped.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_player_commands(
+    mut queue: ResMut<PlayerCommandQueue>,
+    replay_option: Option<ResMut<ReplayCommands>>,
+    recorder_option: Option<ResMut<CommandRecorder>>,
+    mut recruit_events: EventWriter<RecruitUnit>,
+    game_time_query: Query<(&GameDay, &GameTick)>,
+    player_query: Query<(Entity, &GameId), With<Player>>,
+    mut unit_query: Query<CommandUnitQuery, With<Unit>>,
+    city_query: Query<(Entity, &GameId, Option<&OfPlayer>), With<City>>,
+) {
+    let (GameDay(day), GameTick(tick)) = game_time_query.single();
+    let commands = match replay_option {
+        Some(mut replay) => {
+            queue.pending.clear();
+            let mut commands = Vec::new();
+            while let Some(recorded) = replay.commands.front() {
+                if (recorded.day, recorded.tick) > (*day, *tick) {
+                    break;
+                }
+                commands.push(replay.commands.pop_front().unwrap().command);
+            }
+            commands
+        }
+        None => std::mem::take(&mut queue.pending),
+    };
+    if commands.is_empty() {
+        return;
+    }
+    if let Some(mut recorder) = recorder_option {
+        recorder.record(*day, *tick, &commands);
+    }
+
+    let players: HashMap<GameId, Entity> = player_query
+        .iter()
+        .map(|(entity, id)| (*id, entity))
+        .collect();
+    for PlayerCommand { player, command } in commands {
+        let player_entity = match players.get(&player) {
+            Some(player_entity) => *player_entity,
+            None => continue,
+        };
+        match command {
+            GameCommand::MoveUnit { unit, target } => {
+                if let Some((_, _, mut orders)) = unit_query
+                    .iter_mut()
+                    .find(|(id, OfPlayer(owner), _)| **id == unit && *owner == player_entity)
+                {
+                    orders.new_order(UnitOrder::move_to_position(target));
+                }
+            }
+            GameCommand::RecruitUnit {
+                city,
+                unit_type,
+                rally_point,
+            } => {
+                if let Some((city_entity, _, _)) = city_query
+                    .iter()
+                    .find(|(_, id, owner)| **id == city && *owner == Some(&OfPlayer(player_entity)))
+                {
+                    recruit_events.send(RecruitUnit {
+                        city: city_entity,
+                        unit_type,
+                        rally_point,
+                    });
+                }
+            }
+        }
+    }
+}
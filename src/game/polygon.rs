@@ -0,0 +1,120 @@
+use euclid::default::{Box2D, Point2D};
+
+/// Simple (not self intersecting) polygon that can be concave and have
+/// holes. Used for provinces drawn in Tiled, so coordinates are in pixels
+/// with y going down.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    outline: Vec<Point2D<f32>>,
+    holes: Vec<Vec<Point2D<f32>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonError {
+    /// Less than 3 points or all of them on one line
+    NoArea,
+    /// Some edges cross each other
+    SelfIntersecting,
+}
+
+impl Polygon {
+    pub fn new(outline: Vec<Point2D<f32>>) -> Result<Polygon, PolygonError> {
+        check_ring(&outline)?;
+        Ok(Polygon {
+            outline,
+            holes: Vec::new(),
+        })
+    }
+
+    /// Cut a hole in the polygon. Hole doesn't have to be fully inside,
+    /// points inside the hole are just not in the polygon.
+    pub fn add_hole(&mut self, hole: Vec<Point2D<f32>>) -> Result<(), PolygonError> {
+        check_ring(&hole)?;
+        self.holes.push(hole);
+        Ok(())
+    }
+
+    pub fn outline(&self) -> &[Point2D<f32>] {
+        &self.outline
+    }
+
+    /// Smallest box with the whole outline in it
+    pub fn bounding_box(&self) -> Box2D<f32> {
+        Box2D::from_points(self.outline.iter().copied())
+    }
+
+    pub fn contains_point(&self, point: Point2D<f32>) -> bool {
+        ring_contains_point(&self.outline, point)
+            && !self
+                .holes
+                .iter()
+                .any(|hole| ring_contains_point(hole, point))
+    }
+}
+
+fn edges(ring: &[Point2D<f32>]) -> impl Iterator<Item = (Point2D<f32>, Point2D<f32>)> + '_ {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(start, end)| (*start, *end))
+}
+
+fn check_ring(ring: &[Point2D<f32>]) -> Result<(), PolygonError> {
+    let doubled_area: f32 = edges(ring)
+        .map(|(start, end)| start.x * end.y - end.x * start.y)
+        .sum();
+    if ring.len() < 3 || doubled_area.abs() < f32::EPSILON {
+        return Err(PolygonError::NoArea);
+    }
+    let edges = edges(ring).collect::<Vec<_>>();
+    for (index, first) in edges.iter().enumerate() {
+        // Neighbouring edges share a point, so only ones further away count
+        for (other_index, second) in edges.iter().enumerate().skip(index + 2) {
+            if index == 0 && other_index == edges.len() - 1 {
+                continue;
+            }
+            if segments_intersect(*first, *second) {
+                return Err(PolygonError::SelfIntersecting);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Even-odd rule, casting a ray from the point to the right
+fn ring_contains_point(ring: &[Point2D<f32>], point: Point2D<f32>) -> bool {
+    edges(ring)
+        .filter(|(start, end)| {
+            (start.y > point.y) != (end.y > point.y)
+                && point.x < start.x + (point.y - start.y) * (end.x - start.x) / (end.y - start.y)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+fn orientation(a: Point2D<f32>, b: Point2D<f32>, c: Point2D<f32>) -> f32 {
+    (b - a).cross(c - a)
+}
+
+fn segments_intersect(
+    (a_start, a_end): (Point2D<f32>, Point2D<f32>),
+    (b_start, b_end): (Point2D<f32>, Point2D<f32>),
+) -> bool {
+    let on_segment = |start: Point2D<f32>, end: Point2D<f32>, point: Point2D<f32>| {
+        point.x >= start.x.min(end.x)
+            && point.x <= start.x.max(end.x)
+            && point.y >= start.y.min(end.y)
+            && point.y <= start.y.max(end.y)
+    };
+    let a_to_b_start = orientation(a_start, a_end, b_start);
+    let a_to_b_end = orientation(a_start, a_end, b_end);
+    let b_to_a_start = orientation(b_start, b_end, a_start);
+    let b_to_a_end = orientation(b_start, b_end, a_end);
+    if a_to_b_start * a_to_b_end < 0. && b_to_a_start * b_to_a_end < 0. {
+        return true;
+    }
+    (a_to_b_start == 0. && on_segment(a_start, a_end, b_start))
+        || (a_to_b_end == 0. && on_segment(a_start, a_end, b_end))
+        || (b_to_a_start == 0. && on_segment(b_start, b_end, a_start))
+        || (b_to_a_end == 0. && on_segment(b_start, b_end, a_end))
+}
@@ -1,23 +1,49 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use bevy::ecs::system::EntityCommands;
+use serde::{Deserialize, Serialize};
 use strum_macros::{EnumIter, EnumString};
 
 use crate::{
     game::{
-        map::Position,
+        map::{Position, Terrain},
+        recruitment::RecruitmentQueue,
+        site::{self, Site, SiteType},
+        units::{Unit, UnitType},
         world::{
-            CapacityResourceProsumer, CapacityResourceProsumerBundle, CapacityResourceType,
-            OfPlayer, StockpileResourceProsumer, StockpileResourceProsumerBundle,
-            StockpileResourceType,
+            self, CapacityResourceProsumer, CapacityResourceType, GameId, OfPlayer,
+            StockpileResourceProsumer, StockpileResourceType,
         },
+        GameRules,
     },
     prelude::*,
 };
 
 #[derive(Component, Debug)]
 pub struct Province {
+    // Id of the province object in the map, the same after the map is
+    // reloaded and in saves
+    pub object_id: u32,
     pub name: String,
+    // Larger area the province is part of, from `region` property of the map
+    pub region: Option<String>,
+}
+
+/// Player whose units occupy the province, and for how many ticks in a row
+/// they have been the only ones there. Owner of the province is `OfPlayer`,
+/// provinces without it are neutral.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ProvinceOccupation {
+    pub player: Option<Entity>,
+    pub ticks: u32,
+}
+
+/// Province changed owner, sent after the new owner is inserted
+#[derive(Debug, Clone, Copy)]
+pub struct ProvinceOwnerChanged {
+    pub province: Entity,
+    pub previous: Option<Entity>,
+    pub owner: Entity,
 }
 
 #[derive(Component, Clone, Copy, Debug)]
@@ -32,7 +58,9 @@ impl Default for InProvince {
 #[derive(Component, Debug, Default)]
 pub struct City {}
 
-#[derive(Component, Clone, Copy, Debug, EnumString, EnumIter, Default)]
+#[derive(
+    Component, Clone, Copy, Debug, Hash, EnumString, EnumIter, Default, Serialize, Deserialize,
+)]
 pub enum CityType {
     #[default]
     Empty,
@@ -50,6 +78,7 @@ impl CityType {
                 city_type: CityType::Empty,
                 base_stockpile_prosumers: HashMap::new(),
                 base_capacity_prosumers: HashMap::new(),
+                recruitable_units: Vec::new(),
                 size: (2, 2),
             },
             CityType::MageTower => CityStats {
@@ -65,12 +94,18 @@ impl CityType {
                     (CapacityResourceType::Nature, 5),
                     (CapacityResourceType::Sun, 5),
                 ]),
+                recruitable_units: vec![
+                    UnitType::Skeleton,
+                    UnitType::DeathKnight,
+                    UnitType::GiantSpider,
+                ],
                 size: (2, 2),
             },
             CityType::City => CityStats {
                 city_type: CityType::City,
                 base_stockpile_prosumers: HashMap::from([(StockpileResourceType::Gold, 15.)]),
                 base_capacity_prosumers: HashMap::new(),
+                recruitable_units: vec![UnitType::Skeleton],
                 size: (2, 2),
             },
         }
@@ -80,9 +115,11 @@ impl CityType {
 #[derive(Bundle, Debug, Default)]
 pub struct CityBundle {
     pub city: City,
+    pub id: GameId,
     pub province: InProvince,
     pub position: Position,
     pub city_type: CityType,
+    pub recruitment: RecruitmentQueue,
 }
 
 #[derive(Component, Debug, Default)]
@@ -98,19 +135,20 @@ pub struct CityTileBundle {
 impl CityBundle {
     pub fn new_empty_city(
         entity: &mut EntityCommands,
-        player_entity: Entity,
+        id: GameId,
+        player_option: Option<Entity>,
         city_stats: CityStats,
         province: Entity,
         position: Position,
     ) -> Entity {
         entity
             .insert_bundle(CityBundle {
+                id,
                 province: InProvince(province),
                 position,
                 city_type: city_stats.city_type,
                 ..Default::default()
             })
-            .insert(super::world::OfPlayer(player_entity))
             .insert(ui::Selectable {})
             .with_children(|builder| {
                 for x in 0..city_stats.size.0 {
@@ -122,36 +160,183 @@ impl CityBundle {
                         });
                     }
                 }
-                for (resource, amount) in &city_stats.base_stockpile_prosumers {
-                    builder
-                        .spawn()
-                        .insert_bundle(StockpileResourceProsumerBundle {
-                            player: OfPlayer(player_entity),
-                            resource: *resource,
-                            prosumer: StockpileResourceProsumer(*amount),
-                        })
-                        .insert(OfPlayer(player_entity));
-                }
-
-                for (resource, amount) in &city_stats.base_capacity_prosumers {
-                    builder
-                        .spawn()
-                        .insert_bundle(CapacityResourceProsumerBundle {
-                            player: OfPlayer(player_entity),
-                            resource: *resource,
-                            prosumer: CapacityResourceProsumer(*amount),
-                        })
-                        .insert(OfPlayer(player_entity));
-                }
-            })
-            .id()
+            });
+        // Neutral cities don't produce anything
+        if let Some(player_entity) = player_option {
+            set_city_owner(entity, &city_stats, player_entity);
+        }
+        entity.id()
     }
 }
 
+/// Give city to the player, spawning prosumers of the city for them. City
+/// should not have an owner already, prosumers of previous owner need to be
+/// despawned first.
+pub fn set_city_owner(entity: &mut EntityCommands, city_stats: &CityStats, player_entity: Entity) {
+    world::set_owner_with_prosumers(
+        entity,
+        &city_stats.base_stockpile_prosumers,
+        &city_stats.base_capacity_prosumers,
+        player_entity,
+    );
+}
+
 #[derive(Debug)]
 pub struct CityStats {
     pub city_type: CityType,
     pub base_stockpile_prosumers: HashMap<StockpileResourceType, f32>,
     pub base_capacity_prosumers: HashMap<CapacityResourceType, i32>,
+    pub recruitable_units: Vec<UnitType>,
     pub size: (usize, usize),
 }
+
+/// Province is owned by whoever controls its city (first one by id, if it
+/// has several). Otherwise ownership changes by occupation: when units of a
+/// single player other than the owner are in the province for
+/// `GameRules::province_occupation_ticks` ticks in a row, they take the
+/// province together with its cities and sites.
+#[allow(clippy::too_many_arguments)]
+pub fn update_province_owners(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    mut owner_events: EventWriter<ProvinceOwnerChanged>,
+    mut province_query: Query<
+        (
+            Entity,
+            Option<&OfPlayer>,
+            &mut ProvinceOccupation,
+            Option<&Children>,
+        ),
+        With<Province>,
+    >,
+    terrain_query: Query<(&Position, &InProvince), With<Terrain>>,
+    unit_query: Query<(&Position, &OfPlayer), With<Unit>>,
+    city_query: Query<(&GameId, Option<&OfPlayer>), With<City>>,
+    holding_query: Query<
+        (Option<&CityType>, Option<&SiteType>, Option<&Children>),
+        Or<(With<City>, With<Site>)>,
+    >,
+    prosumer_query: Query<
+        (),
+        Or<(
+            With<StockpileResourceProsumer>,
+            With<CapacityResourceProsumer>,
+        )>,
+    >,
+) {
+    let province_at = terrain_query
+        .iter()
+        .map(|(position, InProvince(province))| (*position, *province))
+        .collect::<HashMap<_, _>>();
+    let mut players_in: HashMap<Entity, HashSet<Entity>> = HashMap::new();
+    for (position, OfPlayer(player)) in unit_query.iter() {
+        if let Some(province) = province_at.get(position) {
+            players_in.entry(*province).or_default().insert(*player);
+        }
+    }
+
+    for (province_entity, owner_option, mut occupation, children_option) in
+        province_query.iter_mut()
+    {
+        let owner = owner_option.map(|OfPlayer(owner)| *owner);
+        let holdings = children_option
+            .map(|children| {
+                children
+                    .iter()
+                    .filter(|child| holding_query.get(**child).is_ok())
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let city_owner = holdings
+            .iter()
+            .filter_map(|holding| city_query.get(*holding).ok())
+            .min_by_key(|(id, _)| **id)
+            .and_then(|(_, player_option)| player_option.map(|OfPlayer(player)| *player));
+
+        let mut new_owner = city_owner.filter(|city_owner| Some(*city_owner) != owner);
+        let occupier = match players_in.get(&province_entity) {
+            Some(players) if players.len() == 1 => players
+                .iter()
+                .next()
+                .copied()
+                .filter(|player| Some(*player) != owner),
+            _ => None,
+        };
+        match occupier {
+            Some(player) if new_owner.is_none() => {
+                if occupation.player == Some(player) {
+                    occupation.ticks += 1;
+                } else {
+                    *occupation = ProvinceOccupation {
+                        player: Some(player),
+                        ticks: 1,
+                    };
+                }
+                if occupation.ticks >= rules.province_occupation_ticks {
+                    *occupation = ProvinceOccupation::default();
+                    new_owner = Some(player);
+                    for holding in holdings.iter() {
+                        take_holding(
+                            &mut commands,
+                            *holding,
+                            player,
+                            &holding_query,
+                            &prosumer_query,
+                        );
+                    }
+                }
+            }
+            _ => {
+                if occupation.player.is_some() {
+                    *occupation = ProvinceOccupation::default();
+                }
+            }
+        }
+
+        if let Some(new_owner) = new_owner {
+            commands.entity(province_entity).insert(OfPlayer(new_owner));
+            owner_events.send(ProvinceOwnerChanged {
+                province: province_entity,
+                previous: owner,
+                owner: new_owner,
+            });
+        }
+    }
+}
+
+/// Move city or site to a new owner, replacing prosumers of the old one.
+/// Recruits the old owner paid for are dropped with the city's queue.
+fn take_holding(
+    commands: &mut Commands,
+    holding: Entity,
+    player: Entity,
+    holding_query: &Query<
+        (Option<&CityType>, Option<&SiteType>, Option<&Children>),
+        Or<(With<City>, With<Site>)>,
+    >,
+    prosumer_query: &Query<
+        (),
+        Or<(
+            With<StockpileResourceProsumer>,
+            With<CapacityResourceProsumer>,
+        )>,
+    >,
+) {
+    let (city_type_option, site_type_option, children_option) = match holding_query.get(holding) {
+        Ok(holding_items) => holding_items,
+        Err(_) => return,
+    };
+    for child in children_option.iter().flat_map(|children| children.iter()) {
+        if prosumer_query.get(*child).is_ok() {
+            commands.entity(*child).despawn_recursive();
+        }
+    }
+    let mut entity = commands.entity(holding);
+    if let Some(city_type) = city_type_option {
+        entity.insert(RecruitmentQueue::default());
+        set_city_owner(&mut entity, &city_type.get_city_stats(), player);
+    } else if let Some(site_type) = site_type_option {
+        site::set_site_owner(&mut entity, *site_type, player);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    game::{
+        map::{MapTerrain, Position, TerrainTop, TerrainType},
+        movement::TerrainMovementClass,
+    },
+    prelude::*,
+};
+
+/// How two neighbouring provinces are connected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProvinceConnection {
+    // Number of tile sides the provinces share
+    pub border_length: u32,
+    // Some shared side has a river on either tile
+    pub river: bool,
+    // Some shared side has a road or bridge on both tiles
+    pub road: bool,
+    // Some shared side has water on both tiles
+    pub sea: bool,
+}
+
+/// Provinces that share a tile side with each other, built from the terrain
+/// when the map is spawned and kept on the world entity
+#[derive(Component, Debug, Default)]
+pub struct ProvinceGraph {
+    // Keyed by `Province::object_id`, not by entity, so that iterating is
+    // the same on every run and after loading a save
+    neighbors: BTreeMap<u32, BTreeMap<u32, ProvinceConnection>>,
+    entities: BTreeMap<u32, Entity>,
+    object_ids: HashMap<Entity, u32>,
+}
+
+impl ProvinceGraph {
+    /// `provinces` are (object id, entity) of every province, tiles refer to
+    /// them by index
+    pub fn new(
+        terrain: &MapTerrain,
+        provinces: &[(u32, Entity)],
+        tile_provinces: impl IntoIterator<Item = (Position, usize)>,
+    ) -> ProvinceGraph {
+        let width = terrain.width();
+        let height = terrain.height();
+        let mut tiles = vec![None; (width * height) as usize];
+        for (position, province) in tile_provinces {
+            if position.x < width && position.y < height {
+                tiles[(position.y * width + position.x) as usize] = Some(provinces[province].0);
+            }
+        }
+        let province_at = |position: &Position| tiles[(position.y * width + position.x) as usize];
+
+        let mut graph = ProvinceGraph {
+            entities: provinces.iter().copied().collect(),
+            object_ids: provinces
+                .iter()
+                .map(|(object_id, entity)| (*entity, *object_id))
+                .collect(),
+            ..Default::default()
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let position = Position::new(x, y);
+                let province = match province_at(&position) {
+                    Some(province) => province,
+                    None => continue,
+                };
+                // Every side is looked at once, from the tile west or south of it
+                for direction in [Direction::East, Direction::North] {
+                    let neighbor = match terrain.neighbor(&position, &direction) {
+                        Some(neighbor) => neighbor,
+                        None => continue,
+                    };
+                    let neighbor_province = match province_at(&neighbor) {
+                        Some(neighbor_province) if neighbor_province != province => {
+                            neighbor_province
+                        }
+                        _ => continue,
+                    };
+                    if let (Some(tile), Some(neighbor_tile)) =
+                        (terrain.get(&position), terrain.get(&neighbor))
+                    {
+                        graph.add_side(province, neighbor_province, tile, neighbor_tile);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    fn add_side(
+        &mut self,
+        province: u32,
+        neighbor_province: u32,
+        (base, top): (TerrainType, TerrainTop),
+        (neighbor_base, neighbor_top): (TerrainType, TerrainTop),
+    ) {
+        let is_water = |base| TerrainMovementClass::from(base) == TerrainMovementClass::Water;
+        let has_road =
+            |top: &TerrainTop| matches!(top, TerrainTop::Road(_) | TerrainTop::RiverWithBridge(_));
+        let river = top.is_river() || neighbor_top.is_river();
+        let road = has_road(&top) && has_road(&neighbor_top);
+        let sea = is_water(base) && is_water(neighbor_base);
+        for (from, to) in [(province, neighbor_province), (neighbor_province, province)] {
+            let connection = self
+                .neighbors
+                .entry(from)
+                .or_default()
+                .entry(to)
+                .or_default();
+            connection.border_length += 1;
+            connection.river |= river;
+            connection.road |= road;
+            connection.sea |= sea;
+        }
+    }
+
+    /// Neighbouring provinces with their connections, ordered by object id
+    pub fn neighbors(
+        &self,
+        province: Entity,
+    ) -> impl Iterator<Item = (Entity, &ProvinceConnection)> + '_ {
+        self.object_ids
+            .get(&province)
+            .and_then(|object_id| self.neighbors.get(object_id))
+            .into_iter()
+            .flat_map(|neighbors| {
+                neighbors
+                    .iter()
+                    .map(|(object_id, connection)| (self.entities[object_id], connection))
+            })
+    }
+
+    pub fn connection(&self, province: Entity, other: Entity) -> Option<&ProvinceConnection> {
+        self.neighbors
+            .get(self.object_ids.get(&province)?)?
+            .get(self.object_ids.get(&other)?)
+    }
+
+    pub fn are_neighbors(&self, province: Entity, other: Entity) -> bool {
+        self.connection(province, other).is_some()
+    }
+}
@@ -0,0 +1,68 @@
+use euclid::default::Point2D;
+
+use crate::game::load_map::MapProvince;
+
+// Tiled map tiles are 16x16 pixels
+const TILE_SIZE: f32 = 16.;
+
+/// Provinces whose bounding box covers each tile of the map, built once per
+/// map. Finding the province of a point then only checks polygons that are
+/// near it, instead of all of them. Coordinates are Tiled pixels.
+pub struct ProvinceIndex {
+    width: u32,
+    height: u32,
+    // Indices in provinces list, for every tile row by row
+    tiles: Vec<Vec<usize>>,
+}
+
+impl ProvinceIndex {
+    pub fn new(provinces: &[MapProvince], width: u32, height: u32) -> ProvinceIndex {
+        let mut tiles = vec![Vec::new(); (width * height) as usize];
+        let tile_range = |min: f32, max: f32, size: u32| {
+            let first = (min / TILE_SIZE).floor().max(0.) as u32;
+            let last = ((max / TILE_SIZE).floor() as i64).min(size as i64 - 1);
+            first..(last + 1).max(0) as u32
+        };
+        for (index, province) in provinces.iter().enumerate() {
+            let bounding_box = province.polygon.bounding_box();
+            for y in tile_range(bounding_box.min.y, bounding_box.max.y, height) {
+                for x in tile_range(bounding_box.min.x, bounding_box.max.x, width) {
+                    tiles[(y * width + x) as usize].push(index);
+                }
+            }
+        }
+        ProvinceIndex {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Indices of all provinces that contain the point, in `provinces` order
+    pub fn provinces_at<'a>(
+        &'a self,
+        provinces: &'a [MapProvince],
+        point: Point2D<f32>,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let candidates = if point.x < 0. || point.y < 0. {
+            &[][..]
+        } else {
+            let x = (point.x / TILE_SIZE) as u32;
+            let y = (point.y / TILE_SIZE) as u32;
+            if x < self.width && y < self.height {
+                &self.tiles[(y * self.width + x) as usize][..]
+            } else {
+                &[][..]
+            }
+        };
+        candidates
+            .iter()
+            .copied()
+            .filter(move |index| provinces[*index].polygon.contains_point(point))
+    }
+
+    /// First province that contains the point
+    pub fn province_at(&self, provinces: &[MapProvince], point: Point2D<f32>) -> Option<usize> {
+        self.provinces_at(provinces, point).next()
+    }
+}
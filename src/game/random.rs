@@ -0,0 +1,70 @@
+use std::{
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Systems that roll dice, every one gets its own stream of random numbers
+/// so that rolling more in one doesn't change what others get
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RngStream {
+    Combat,
+    Events,
+    Ai,
+}
+
+/// Random number generator of the game. Seed is chosen at game setup, state
+/// of every stream is saved with the game, so loaded games and replays roll
+/// the same numbers.
+///
+/// Systems in `GameTickStageLabel` sets take it as `ResMut<GameRng>` and
+/// use `rand::Rng` methods on their stream:
+/// `game_rng.stream(RngStream::Combat).gen_range(0..10)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    // Created on first use, so that adding a stream doesn't touch others
+    streams: BTreeMap<RngStream, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        GameRng {
+            seed,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(stream as u64);
+            rng
+        })
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+/// Hashes seed and how far every stream has got, for state checksums
+impl Hash for GameRng {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.seed.hash(state);
+        for (stream, rng) in self.streams.iter() {
+            stream.hash(state);
+            rng.get_word_pos().hash(state);
+        }
+    }
+}
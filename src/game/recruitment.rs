@@ -0,0 +1,208 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        map::{MapTerrain, Position},
+        province::{City, CityType},
+        units::{UnitBundle, UnitOrder, UnitOrders, UnitType},
+        world::{
+            CapacityResourceAmount, CapacityResourceType, GameId, GameIds, OfPlayer,
+            StockpileResourceAmount, StockpileResourceType,
+        },
+        GameTick,
+    },
+    prelude::*,
+};
+
+/// Request to recruit a unit in a city, sent when player command to recruit
+/// is applied. Cost is paid when it's queued.
+#[derive(Debug, Clone, Copy)]
+pub struct RecruitUnit {
+    pub city: Entity,
+    pub unit_type: UnitType,
+    // Where the unit should go once it's recruited
+    pub rally_point: Option<Position>,
+}
+
+/// Units being recruited in the city, first one is in progress
+#[derive(Component, Debug, Default, Clone, Hash, Serialize, Deserialize)]
+pub struct RecruitmentQueue {
+    pub queue: VecDeque<QueuedRecruit>,
+}
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
+pub struct QueuedRecruit {
+    pub unit_type: UnitType,
+    pub ticks_left: u32,
+    pub rally_point: Option<Position>,
+}
+
+type RecruitingCityQuery = (
+    Option<&'static OfPlayer>,
+    &'static CityType,
+    &'static mut RecruitmentQueue,
+);
+
+/// Check that owner of the city can pay for the unit and has free capacity
+/// for it, then take the cost and queue the unit
+pub fn recruit_units(
+    mut recruit_events: EventReader<RecruitUnit>,
+    mut city_query: Query<RecruitingCityQuery, With<City>>,
+    mut stockpile_query: Query<(
+        &OfPlayer,
+        &StockpileResourceType,
+        &mut StockpileResourceAmount,
+    )>,
+    capacity_query: Query<(&OfPlayer, &CapacityResourceType, &CapacityResourceAmount)>,
+) {
+    for recruit in recruit_events.iter() {
+        let player = match city_query.get(recruit.city) {
+            Ok((Some(OfPlayer(player)), city_type, _))
+                if city_type
+                    .get_city_stats()
+                    .recruitable_units
+                    .contains(&recruit.unit_type) =>
+            {
+                *player
+            }
+            _ => continue,
+        };
+        let unit_stats = recruit.unit_type.get_unit_stats();
+
+        let mut free_capacity: HashMap<CapacityResourceType, i32> = HashMap::new();
+        for (OfPlayer(capacity_player), resource, amount) in capacity_query.iter() {
+            if *capacity_player == player {
+                *free_capacity.entry(*resource).or_insert(0) += amount.free;
+            }
+        }
+        // Queued units don't have their prosumers yet, but will take the capacity
+        for (city_player, _, recruitment_queue) in city_query.iter() {
+            if city_player == Some(&OfPlayer(player)) {
+                for queued in recruitment_queue.queue.iter() {
+                    for (resource, amount) in queued.unit_type.get_unit_stats().capacity_cost {
+                        *free_capacity.entry(resource).or_insert(0) += amount;
+                    }
+                }
+            }
+        }
+        let has_capacity = unit_stats.capacity_cost.iter().all(|(resource, amount)| {
+            free_capacity.get(resource).copied().unwrap_or(0) + amount >= 0
+        });
+
+        let can_pay = unit_stats.cost.iter().all(|(resource, cost)| {
+            stockpile_query.iter().any(
+                |(OfPlayer(stockpile_player), stockpile_resource, amount)| {
+                    *stockpile_player == player
+                        && stockpile_resource == resource
+                        && amount.0 >= *cost
+                },
+            )
+        });
+
+        if !has_capacity || !can_pay {
+            continue;
+        }
+
+        for (OfPlayer(stockpile_player), resource, mut amount) in stockpile_query.iter_mut() {
+            if *stockpile_player == player {
+                if let Some(cost) = unit_stats.cost.get(resource) {
+                    amount.0 -= cost;
+                }
+            }
+        }
+        let (_, _, mut recruitment_queue) = city_query.get_mut(recruit.city).unwrap();
+        recruitment_queue.queue.push_back(QueuedRecruit {
+            unit_type: recruit.unit_type,
+            ticks_left: unit_stats.recruit_ticks,
+            rally_point: recruit.rally_point,
+        });
+    }
+}
+
+type RecruitmentProgressQuery = (
+    &'static GameId,
+    &'static OfPlayer,
+    &'static Position,
+    &'static CityType,
+    &'static mut RecruitmentQueue,
+);
+
+pub fn progress_recruitment(
+    mut commands: Commands,
+    mut game_ids: ResMut<GameIds>,
+    game_tick_query: Query<(ChangeTrackers<GameTick>, &MapTerrain)>,
+    mut city_query: Query<RecruitmentProgressQuery, With<City>>,
+) {
+    let (game_tick_change_tracker, map_terrain) = game_tick_query.single();
+    if !game_tick_change_tracker.is_changed() {
+        return;
+    }
+    // Units get their ids in order of cities, so that it's same every run
+    let mut cities = city_query.iter_mut().collect::<Vec<_>>();
+    cities.sort_unstable_by_key(|(city_id, ..)| **city_id);
+    for (_, OfPlayer(player), city_position, city_type, recruitment_queue) in cities.iter_mut() {
+        let finished = match recruitment_queue.queue.front_mut() {
+            Some(queued) => {
+                queued.ticks_left = queued.ticks_left.saturating_sub(1);
+                queued.ticks_left == 0
+            }
+            None => false,
+        };
+        if !finished {
+            continue;
+        }
+        let queued = recruitment_queue.queue.pop_front().unwrap();
+        let position = spawn_position(
+            map_terrain,
+            city_position,
+            city_type.get_city_stats().size,
+            queued.unit_type,
+        );
+        let mut unit = commands.spawn();
+        let unit_entity = UnitBundle::insert_full(
+            &mut unit,
+            game_ids.next(),
+            *player,
+            queued.unit_type,
+            position,
+        );
+        if let Some(rally_point) = queued.rally_point {
+            let mut orders = UnitOrders::default();
+            orders.new_order(UnitOrder::move_to_position(rally_point));
+            commands.entity(unit_entity).insert(orders);
+        }
+        commands.entity(*player).add_child(unit_entity);
+    }
+}
+
+/// First tile around the city that unit can enter, going from bottom-left
+/// corner. Falls back to the city position if it's surrounded.
+fn spawn_position(
+    map_terrain: &MapTerrain,
+    city_position: &Position,
+    (width, height): (usize, usize),
+    unit_type: UnitType,
+) -> Position {
+    let rules = unit_type
+        .get_unit_stats()
+        .movement_type
+        .get_movement_rules();
+    let min_x = city_position.x as i64 - 1;
+    let min_y = city_position.y as i64 - 1;
+    let max_x = city_position.x as i64 + width as i64;
+    let max_y = city_position.y as i64 + height as i64;
+    (min_y..=max_y)
+        .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+        .filter(|(x, y)| *x == min_x || *x == max_x || *y == min_y || *y == max_y)
+        .filter(|(x, y)| *x >= 0 && *y >= 0)
+        .map(|(x, y)| Position::new(x as u32, y as u32))
+        .find(|position| {
+            map_terrain
+                .get(position)
+                .and_then(|(base, top)| rules.tile_cost(base, top))
+                .is_some()
+        })
+        .unwrap_or(*city_position)
+}
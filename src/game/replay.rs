@@ -0,0 +1,190 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        player_commands::{PlayerCommand, RecordedCommand, ReplayCommands},
+        save::{LoadGame, SaveGame, SaveGameError, SAVE_VERSION},
+    },
+    prelude::*,
+};
+
+pub const REPLAY_PATH: &str = "./saves/last_game.replay.ron";
+
+/// Replays are RON too, so they are told apart from saves by the name
+pub fn is_replay_path(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(".replay.ron")
+}
+
+/// Game state at the start of a game and every command players gave during
+/// it. Loading initial state and applying the commands at the same ticks
+/// gives the same game.
+///
+/// In the file, first line has the version and initial state, then every
+/// command is on its own line, so that commands can be appended as the game
+/// goes without writing the initial state again.
+#[derive(Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub initial: SaveGame,
+    pub commands: Vec<RecordedCommand>,
+}
+
+#[derive(Serialize)]
+struct ReplayHeaderRef<'a> {
+    version: u32,
+    initial: &'a SaveGame,
+}
+
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    initial: SaveGame,
+}
+
+#[derive(Deserialize)]
+struct ReplayVersion {
+    version: u32,
+}
+
+impl Replay {
+    pub fn new(initial: SaveGame) -> Replay {
+        Replay {
+            version: SAVE_VERSION,
+            initial,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveGameError> {
+        let io_error = |source| SaveGameError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut serialized = ron::to_string(&ReplayHeaderRef {
+            version: self.version,
+            initial: &self.initial,
+        })
+        .map_err(|error| SaveGameError::Serialize(error.to_string()))?;
+        serialized.push('\n');
+        for command in self.commands.iter() {
+            serialized.push_str(&command_line(command)?);
+        }
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io_error)?;
+        }
+        fs::write(path, serialized).map_err(io_error)
+    }
+
+    pub fn read(path: &Path) -> Result<Replay, SaveGameError> {
+        let serialized = fs::read_to_string(path).map_err(|source| SaveGameError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut lines = serialized.lines().filter(|line| !line.trim().is_empty());
+        let header_line = lines.next().unwrap_or_default();
+        let ReplayVersion { version } = ron::from_str(header_line).map_err(deserialize_error)?;
+        if version != SAVE_VERSION {
+            return Err(SaveGameError::UnsupportedVersion(version));
+        }
+        let ReplayHeader { version, initial } =
+            ron::from_str(header_line).map_err(deserialize_error)?;
+        initial.validate()?;
+        let commands = lines
+            .map(|line| ron::from_str(line).map_err(deserialize_error))
+            .collect::<Result<Vec<RecordedCommand>, _>>()?;
+        Ok(Replay {
+            version,
+            initial,
+            commands,
+        })
+    }
+
+    /// Insert resources that make the app load initial state of the replay
+    /// and apply its commands instead of player ones
+    pub fn insert_into(self, app: &mut App) {
+        app.insert_resource(ReplayCommands {
+            commands: self.commands.into(),
+        })
+        .insert_resource(LoadGame(self.initial));
+    }
+}
+
+fn deserialize_error(error: impl ToString) -> SaveGameError {
+    SaveGameError::Deserialize(error.to_string())
+}
+
+fn command_line(command: &RecordedCommand) -> Result<String, SaveGameError> {
+    ron::to_string(command)
+        .map(|serialized| serialized + "\n")
+        .map_err(|error| SaveGameError::Serialize(error.to_string()))
+}
+
+/// Inserting it makes the game record itself to the path once it's loaded
+pub struct RecordReplay(pub PathBuf);
+
+/// Replay being recorded. Initial state is written when recording starts,
+/// commands are appended every time they are applied so that the replay is
+/// there even if the game crashes.
+pub struct CommandRecorder {
+    path: PathBuf,
+    file: fs::File,
+}
+
+impl CommandRecorder {
+    pub fn record(&mut self, day: u32, tick: usize, commands: &[PlayerCommand]) {
+        let lines = commands
+            .iter()
+            .map(|command| {
+                command_line(&RecordedCommand {
+                    day,
+                    tick,
+                    command: command.clone(),
+                })
+            })
+            .collect::<Result<String, _>>();
+        let result = lines.and_then(|lines| {
+            self.file
+                .write_all(lines.as_bytes())
+                .map_err(|source| SaveGameError::Io {
+                    path: self.path.clone(),
+                    source,
+                })
+        });
+        if let Err(error) = result {
+            error!("{}", error);
+        }
+    }
+}
+
+/// Snapshot the world as initial state of the replay, at the end of the stage
+pub fn start_recording(mut commands: Commands, record_replay: Res<RecordReplay>) {
+    let path = record_replay.0.clone();
+    commands.add(move |world: &mut World| {
+        let replay = match SaveGame::from_world(world) {
+            Ok(initial_state) => Replay::new(initial_state),
+            Err(error) => {
+                error!("Game isn't recorded, {}", error);
+                return;
+            }
+        };
+        let file_result = replay.write(&path).and_then(|()| {
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .map_err(|source| SaveGameError::Io {
+                    path: path.clone(),
+                    source,
+                })
+        });
+        match file_result {
+            Ok(file) => world.insert_resource(CommandRecorder { path, file }),
+            Err(error) => error!("Game isn't recorded, {}", error),
+        }
+    });
+}
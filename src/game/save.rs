@@ -0,0 +1,617 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    game::{
+        map::{
+            Map, MapTerrain, Position, Terrain, TerrainBase, TerrainBundle, TerrainTop, TerrainType,
+        },
+        province::{City, CityBundle, CityType, InProvince, Province, ProvinceOccupation},
+        province_graph::ProvinceGraph,
+        random::GameRng,
+        recruitment::RecruitmentQueue,
+        site::{Site, SiteBundle, SiteType},
+        units::{Unit, UnitBundle, UnitFigure, UnitFigureHealth, UnitOrders, UnitType},
+        world::{
+            CapacityResourceProsumer, CapacityResourceType, GameId, GameIds, OfPlayer, Player,
+            PlayerBundle, PlayerCapacityBundle, PlayerColor, PlayerName, PlayerStockpileBundle,
+            StockpileResourceAmount, StockpileResourceType,
+        },
+        FirstDay, GameDay, GameRules, GameRulesError, GameTick, GameWorld, GameWorldBundle,
+    },
+    prelude::*,
+};
+
+/// Bump when saved data changes in incompatible way
+pub const SAVE_VERSION: u32 = 10;
+
+pub const QUICKSAVE_PATH: &str = "./saves/quicksave.ron";
+
+#[derive(Debug, Error)]
+pub enum SaveGameError {
+    #[error("couldn't access save file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("couldn't serialize save: {0}")]
+    Serialize(String),
+    #[error("couldn't parse save: {0}")]
+    Deserialize(String),
+    #[error("save version {0} is not supported, expected {SAVE_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("save has invalid rules: {0}")]
+    Rules(GameRulesError),
+    #[error("game can't be saved before the world is loaded, {0} is missing")]
+    WorldNotLoaded(&'static str),
+    #[error("save refers to {kind} {index}, but has only {count}")]
+    InvalidIndex {
+        kind: &'static str,
+        index: usize,
+        count: usize,
+    },
+}
+
+/// Inserting this resource before `EngineState::LoadingWorld` makes the game
+/// spawn world from the save instead of loading the map
+pub struct LoadGame(pub SaveGame);
+
+/// Whole game state. Entities are referred to by their index in the
+/// corresponding list (eg `player: 0` is first player in `players`), so they
+/// can be re-linked when spawned into a new world.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub game_day: u32,
+    pub game_tick: usize,
+    pub first_day: bool,
+    pub rules: GameRules,
+    pub ids: GameIds,
+    pub rng: GameRng,
+    pub width: u32,
+    pub height: u32,
+    pub players: Vec<SavedPlayer>,
+    pub provinces: Vec<SavedProvince>,
+    pub terrain: Vec<SavedTerrain>,
+    pub cities: Vec<SavedCity>,
+    pub sites: Vec<SavedSite>,
+    pub units: Vec<SavedUnit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub id: GameId,
+    pub name: String,
+    pub color: Color,
+    // Player that is controlled by this app
+    pub viewer: bool,
+    pub stockpiles: Vec<(StockpileResourceType, f32)>,
+    pub capacities: Vec<CapacityResourceType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedProvince {
+    pub object_id: u32,
+    pub name: String,
+    pub region: Option<String>,
+    // None for neutral provinces
+    pub owner: Option<usize>,
+    // (player, ticks) of the player occupying the province
+    pub occupation: Option<(usize, u32)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedTerrain {
+    pub province: usize,
+    pub position: Position,
+    pub base: TerrainType,
+    pub top: TerrainTop,
+}
+
+/// City tiles and prosumers are derived from city stats, so they aren't saved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedCity {
+    pub id: GameId,
+    // None for neutral cities
+    pub player: Option<usize>,
+    pub province: usize,
+    pub position: Position,
+    pub city_type: CityType,
+    pub recruitment: RecruitmentQueue,
+}
+
+/// Site prosumers are derived from site stats, so they aren't saved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedSite {
+    pub id: GameId,
+    // None for neutral sites
+    pub player: Option<usize>,
+    pub province: usize,
+    pub position: Position,
+    pub site_type: SiteType,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedUnit {
+    pub id: GameId,
+    pub player: usize,
+    pub unit_type: UnitType,
+    pub position: Position,
+    // (index, health) of figures that are still alive
+    pub figures: Vec<(usize, u32)>,
+    pub orders: UnitOrders,
+}
+
+/// Only version is read first, so that older saves give a clear error
+/// instead of a parse error about missing fields
+#[derive(Deserialize)]
+struct SaveGameVersion {
+    version: u32,
+}
+
+impl SaveGame {
+    pub fn from_world(world: &mut World) -> Result<SaveGame, SaveGameError> {
+        let (game_day, game_tick, first_day) = world
+            .query_filtered::<(&GameDay, &GameTick, &FirstDay), With<GameWorld>>()
+            .iter(world)
+            .next()
+            .map(|(GameDay(day), GameTick(tick), FirstDay(first_day))| (*day, *tick, *first_day))
+            .ok_or(SaveGameError::WorldNotLoaded("game world"))?;
+        let (width, height) = world
+            .query::<&Map>()
+            .iter(world)
+            .next()
+            .map(|map| (map.width, map.height))
+            .ok_or(SaveGameError::WorldNotLoaded("map"))?;
+
+        let mut viewer_query = world.query_filtered::<Entity, With<ui::Viewer>>();
+        let viewer_entity = viewer_query.iter(world).next();
+
+        let rules = world
+            .get_resource::<GameRules>()
+            .cloned()
+            .unwrap_or_default();
+        let ids = world.get_resource::<GameIds>().cloned().unwrap_or_default();
+        let rng = world.get_resource::<GameRng>().cloned().unwrap_or_default();
+
+        // Everything with an id is saved in id order, so that same game
+        // always makes the same save
+        let mut player_query =
+            world.query_filtered::<(Entity, &GameId, &PlayerName, &PlayerColor), With<Player>>();
+        let mut player_items = player_query.iter(world).collect::<Vec<_>>();
+        player_items.sort_unstable_by_key(|(_, id, _, _)| **id);
+        let mut player_indices: HashMap<Entity, usize> = HashMap::new();
+        let mut players = Vec::new();
+        for (entity, id, PlayerName(name), PlayerColor(color)) in player_items {
+            player_indices.insert(entity, players.len());
+            players.push(SavedPlayer {
+                id: *id,
+                name: name.clone(),
+                color: *color,
+                viewer: viewer_entity == Some(entity),
+                stockpiles: Vec::new(),
+                capacities: Vec::new(),
+            });
+        }
+
+        for (OfPlayer(player), resource, StockpileResourceAmount(amount)) in world
+            .query::<(&OfPlayer, &StockpileResourceType, &StockpileResourceAmount)>()
+            .iter(world)
+        {
+            if let Some(index) = player_indices.get(player) {
+                players[*index].stockpiles.push((*resource, *amount));
+            }
+        }
+
+        for (OfPlayer(player), resource) in world
+            .query_filtered::<(&OfPlayer, &CapacityResourceType), Without<CapacityResourceProsumer>>()
+            .iter(world)
+        {
+            if let Some(index) = player_indices.get(player) {
+                players[*index].capacities.push(*resource);
+            }
+        }
+        for player in players.iter_mut() {
+            player
+                .stockpiles
+                .sort_unstable_by_key(|(resource, _)| *resource);
+            player.capacities.sort_unstable();
+        }
+
+        let mut province_entries = world
+            .query::<(Entity, &Province, Option<&OfPlayer>, &ProvinceOccupation)>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        province_entries.sort_unstable_by_key(|(_, province, _, _)| province.object_id);
+        let mut province_indices: HashMap<Entity, usize> = HashMap::new();
+        let mut provinces = Vec::new();
+        for (entity, province, owner_option, occupation) in province_entries {
+            province_indices.insert(entity, provinces.len());
+            provinces.push(SavedProvince {
+                object_id: province.object_id,
+                name: province.name.clone(),
+                region: province.region.clone(),
+                owner: owner_option
+                    .and_then(|OfPlayer(player)| player_indices.get(player))
+                    .copied(),
+                occupation: occupation
+                    .player
+                    .and_then(|player| Some((*player_indices.get(&player)?, occupation.ticks))),
+            });
+        }
+
+        let mut terrain = world
+            .query_filtered::<(&InProvince, &Position, &TerrainBase, &TerrainTop), With<Terrain>>()
+            .iter(world)
+            .filter_map(|(InProvince(province), position, TerrainBase(base), top)| {
+                Some(SavedTerrain {
+                    province: *province_indices.get(province)?,
+                    position: *position,
+                    base: *base,
+                    top: *top,
+                })
+            })
+            .collect::<Vec<_>>();
+        terrain.sort_unstable_by_key(|saved_terrain| {
+            (saved_terrain.position.y, saved_terrain.position.x)
+        });
+
+        let mut cities = world
+            .query_filtered::<(
+                &GameId,
+                Option<&OfPlayer>,
+                &InProvince,
+                &Position,
+                &CityType,
+                &RecruitmentQueue,
+            ), With<City>>()
+            .iter(world)
+            .filter_map(
+                |(id, player_option, InProvince(province), position, city_type, recruitment)| {
+                    Some(SavedCity {
+                        id: *id,
+                        player: player_option
+                            .and_then(|OfPlayer(player)| player_indices.get(player))
+                            .copied(),
+                        province: *province_indices.get(province)?,
+                        position: *position,
+                        city_type: *city_type,
+                        recruitment: recruitment.clone(),
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
+        cities.sort_unstable_by_key(|saved| saved.id);
+
+        let mut sites = world
+            .query_filtered::<(
+                &GameId,
+                Option<&OfPlayer>,
+                &InProvince,
+                &Position,
+                &SiteType,
+            ), With<Site>>()
+            .iter(world)
+            .filter_map(
+                |(id, player_option, InProvince(province), position, site_type)| {
+                    Some(SavedSite {
+                        id: *id,
+                        player: player_option
+                            .and_then(|OfPlayer(player)| player_indices.get(player))
+                            .copied(),
+                        province: *province_indices.get(province)?,
+                        position: *position,
+                        site_type: *site_type,
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
+        sites.sort_unstable_by_key(|saved| saved.id);
+
+        let mut figure_query = world.query::<(&UnitFigure, &UnitFigureHealth)>();
+        let mut unit_query = world.query_filtered::<(
+            &GameId,
+            &OfPlayer,
+            &UnitType,
+            &Position,
+            &UnitOrders,
+            &Children,
+        ), With<Unit>>();
+        let mut units = unit_query
+            .iter(world)
+            .filter_map(
+                |(id, OfPlayer(player), unit_type, position, orders, children)| {
+                    let mut figures = children
+                        .iter()
+                        .filter_map(|child| {
+                            figure_query
+                                .get(world, *child)
+                                .ok()
+                                .map(|(figure, UnitFigureHealth(health))| (figure.index, *health))
+                        })
+                        .collect::<Vec<_>>();
+                    figures.sort_unstable();
+                    Some(SavedUnit {
+                        id: *id,
+                        player: *player_indices.get(player)?,
+                        unit_type: *unit_type,
+                        position: *position,
+                        figures,
+                        orders: orders.clone(),
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
+        units.sort_unstable_by_key(|saved| saved.id);
+
+        Ok(SaveGame {
+            version: SAVE_VERSION,
+            game_day,
+            game_tick,
+            first_day,
+            rules,
+            ids,
+            rng,
+            width,
+            height,
+            players,
+            provinces,
+            terrain,
+            cities,
+            sites,
+            units,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveGameError> {
+        let io_error = |source| SaveGameError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| SaveGameError::Serialize(error.to_string()))?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io_error)?;
+        }
+        fs::write(path, serialized).map_err(io_error)
+    }
+
+    pub fn read(path: &Path) -> Result<SaveGame, SaveGameError> {
+        let serialized = fs::read_to_string(path).map_err(|source| SaveGameError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let SaveGameVersion { version } = ron::from_str(&serialized)
+            .map_err(|error| SaveGameError::Deserialize(error.to_string()))?;
+        if version != SAVE_VERSION {
+            return Err(SaveGameError::UnsupportedVersion(version));
+        }
+        let save: SaveGame = ron::from_str(&serialized)
+            .map_err(|error| SaveGameError::Deserialize(error.to_string()))?;
+        save.validate()?;
+        Ok(save)
+    }
+
+    /// Check what `spawn` relies on: valid rules and every player and
+    /// province index pointing into its list
+    pub fn validate(&self) -> Result<(), SaveGameError> {
+        self.rules.validate().map_err(SaveGameError::Rules)?;
+        let check = |kind, index: usize, count: usize| {
+            if index < count {
+                Ok(())
+            } else {
+                Err(SaveGameError::InvalidIndex { kind, index, count })
+            }
+        };
+        let players = self.players.len();
+        let provinces = self.provinces.len();
+        for province in &self.provinces {
+            if let Some(owner) = province.owner {
+                check("player", owner, players)?;
+            }
+            if let Some((occupier, _)) = province.occupation {
+                check("player", occupier, players)?;
+            }
+        }
+        for saved_terrain in &self.terrain {
+            check("province", saved_terrain.province, provinces)?;
+        }
+        for saved_city in &self.cities {
+            check("province", saved_city.province, provinces)?;
+            if let Some(player) = saved_city.player {
+                check("player", player, players)?;
+            }
+        }
+        for saved_site in &self.sites {
+            check("province", saved_site.province, provinces)?;
+            if let Some(player) = saved_site.player {
+                check("player", player, players)?;
+            }
+        }
+        for saved_unit in &self.units {
+            check("player", saved_unit.player, players)?;
+        }
+        Ok(())
+    }
+
+    /// Spawn saved world, same hierarchy as `setup_game_world` and `load_map` create
+    pub fn spawn(&self, commands: &mut Commands) {
+        commands.insert_resource(self.rules.clone());
+        commands.insert_resource(self.ids.clone());
+        commands.insert_resource(self.rng.clone());
+        let world_entity = commands
+            .spawn_bundle(GameWorldBundle {
+                game_day: GameDay(self.game_day),
+                game_tick: GameTick(self.game_tick),
+                ..GameWorldBundle::empty()
+            })
+            .insert(FirstDay(self.first_day))
+            .insert(Map {
+                width: self.width,
+                height: self.height,
+            })
+            .id();
+
+        let player_entities = self
+            .players
+            .iter()
+            .map(|saved_player| {
+                let mut player = commands.spawn_bundle(PlayerBundle {
+                    id: saved_player.id,
+                    name: PlayerName(saved_player.name.clone()),
+                    color: PlayerColor(saved_player.color),
+                    ..Default::default()
+                });
+                let player_entity = player.id();
+                player.with_children(|builder| {
+                    for (resource, amount) in &saved_player.stockpiles {
+                        builder.spawn_bundle(PlayerStockpileBundle {
+                            player: OfPlayer(player_entity),
+                            resource: *resource,
+                            amount: StockpileResourceAmount(*amount),
+                        });
+                    }
+                    for resource in &saved_player.capacities {
+                        builder.spawn_bundle(PlayerCapacityBundle {
+                            player: OfPlayer(player_entity),
+                            resource: *resource,
+                            ..Default::default()
+                        });
+                    }
+                });
+                if saved_player.viewer {
+                    player.insert_bundle(ui::ViewerBundle {
+                        ..Default::default()
+                    });
+                }
+                commands.entity(world_entity).add_child(player_entity);
+                player_entity
+            })
+            .collect::<Vec<_>>();
+
+        let province_entities = self
+            .provinces
+            .iter()
+            .map(|saved_province| {
+                let mut province = commands.spawn();
+                province
+                    .insert(Province {
+                        object_id: saved_province.object_id,
+                        name: saved_province.name.clone(),
+                        region: saved_province.region.clone(),
+                    })
+                    .insert(ProvinceOccupation {
+                        player: saved_province
+                            .occupation
+                            .map(|(player, _)| player_entities[player]),
+                        ticks: saved_province.occupation.map_or(0, |(_, ticks)| ticks),
+                    });
+                if let Some(owner) = saved_province.owner {
+                    province.insert(OfPlayer(player_entities[owner]));
+                }
+                let province_entity = province.id();
+                commands.entity(world_entity).add_child(province_entity);
+                province_entity
+            })
+            .collect::<Vec<_>>();
+
+        let mut map_terrain = MapTerrain::new(self.width, self.height);
+        for saved_terrain in &self.terrain {
+            let province_entity = province_entities[saved_terrain.province];
+            map_terrain.set(
+                &saved_terrain.position,
+                saved_terrain.base,
+                saved_terrain.top,
+            );
+            let terrain = commands
+                .spawn_bundle(TerrainBundle {
+                    province: InProvince(province_entity),
+                    position: saved_terrain.position,
+                    base: TerrainBase(saved_terrain.base),
+                    top: saved_terrain.top,
+                    ..Default::default()
+                })
+                .id();
+            commands.entity(province_entity).add_child(terrain);
+        }
+        let province_graph = ProvinceGraph::new(
+            &map_terrain,
+            &self
+                .provinces
+                .iter()
+                .map(|saved_province| saved_province.object_id)
+                .zip(province_entities.iter().copied())
+                .collect::<Vec<_>>(),
+            self.terrain
+                .iter()
+                .map(|saved_terrain| (saved_terrain.position, saved_terrain.province)),
+        );
+        commands
+            .entity(world_entity)
+            .insert(map_terrain)
+            .insert(province_graph);
+
+        for saved_city in &self.cities {
+            let province_entity = province_entities[saved_city.province];
+            let city = CityBundle::new_empty_city(
+                &mut commands.spawn(),
+                saved_city.id,
+                saved_city.player.map(|player| player_entities[player]),
+                saved_city.city_type.get_city_stats(),
+                province_entity,
+                saved_city.position,
+            );
+            commands.entity(city).insert(saved_city.recruitment.clone());
+            commands.entity(province_entity).add_child(city);
+        }
+
+        for saved_site in &self.sites {
+            let province_entity = province_entities[saved_site.province];
+            let site = SiteBundle::new_site(
+                &mut commands.spawn(),
+                saved_site.id,
+                saved_site.player.map(|player| player_entities[player]),
+                saved_site.site_type,
+                province_entity,
+                saved_site.position,
+            );
+            commands.entity(province_entity).add_child(site);
+        }
+
+        for saved_unit in &self.units {
+            let player_entity = player_entities[saved_unit.player];
+            let mut unit = commands.spawn();
+            UnitBundle::insert_with_figures(
+                &mut unit,
+                saved_unit.id,
+                player_entity,
+                saved_unit.unit_type,
+                saved_unit.position,
+                &saved_unit.figures,
+            );
+            let unit_entity = unit.insert(saved_unit.orders.clone()).id();
+            commands.entity(player_entity).add_child(unit_entity);
+        }
+    }
+}
+
+pub fn load_game(mut commands: Commands, load_game: Res<LoadGame>) {
+    load_game.0.spawn(&mut commands);
+    commands.insert_resource(NextState(config::EngineState::LoadingWorld.next()));
+}
+
+/// Save game at the end of the current stage
+pub fn save_game(commands: &mut Commands, path: PathBuf) {
+    commands.add(move |world: &mut World| {
+        match SaveGame::from_world(world).and_then(|save| save.write(&path)) {
+            Ok(()) => info!("Saved game to {}", path.display()),
+            Err(error) => error!("{}", error),
+        }
+    });
+}
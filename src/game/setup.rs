@@ -0,0 +1,278 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+use tiled::PropertyValue;
+
+use crate::{
+    game::{
+        map::Position,
+        random::GameRng,
+        units::{UnitBundle, UnitType},
+        world::{
+            CapacityResourceType, GameIds, OfPlayer, PlayerBundle, PlayerCapacityBundle,
+            PlayerColor, PlayerName, PlayerStockpileBundle, StockpileResourceAmount,
+            StockpileResourceType,
+        },
+        GameRules, GameRulesError,
+    },
+    prelude::*,
+};
+
+#[derive(Debug, Error)]
+pub enum GameSetupError {
+    #[error("couldn't read game setup {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("couldn't parse game setup {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("game setup {path} has invalid rules: {source}")]
+    Rules {
+        path: PathBuf,
+        source: GameRulesError,
+    },
+    #[error("game setup {path} has no players")]
+    NoPlayers { path: PathBuf },
+    #[error("game setup {path} has more than one player named {name}")]
+    DuplicatePlayer { path: PathBuf, name: String },
+    #[error("game setup {path} has more than one viewer: {}", .names.join(", "))]
+    SeveralViewers { path: PathBuf, names: Vec<String> },
+}
+
+/// Who plays the game and what they start with. Inserting it as a resource
+/// overrides setup from the map. Otherwise map can point to a setup file
+/// with `setup` property, relative to the map file. Without either, there
+/// is a single player that owns all the cities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub players: Vec<PlayerSetup>,
+    #[serde(default)]
+    pub rules: GameRules,
+    // Seed of the game random number generator, random if not set
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSetup {
+    pub name: String,
+    pub color: Color,
+    // Player that is controlled by this app, first player if none is
+    #[serde(default)]
+    pub viewer: bool,
+    #[serde(default)]
+    pub stockpiles: Vec<(StockpileResourceType, f32)>,
+    #[serde(default)]
+    pub units: Vec<(UnitType, Position)>,
+    #[serde(default)]
+    pub cities: StartingCities,
+}
+
+/// Cities player owns at start. Cities can also be assigned on the map with
+/// `player` property on city object, which takes precedence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum StartingCities {
+    #[default]
+    None,
+    // City object names
+    Named(Vec<String>),
+    // All cities that no one else owns
+    Rest,
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        GameSetup {
+            players: vec![PlayerSetup {
+                name: "PLAYER".to_string(),
+                color: Color::RED,
+                viewer: true,
+                stockpiles: vec![
+                    (StockpileResourceType::Gold, 100.),
+                    (StockpileResourceType::Wood, 50.),
+                ],
+                units: vec![(UnitType::Skeleton, Position { x: 64, y: 40 })],
+                cities: StartingCities::Rest,
+            }],
+            rules: GameRules::default(),
+            seed: None,
+        }
+    }
+}
+
+impl GameSetup {
+    pub fn read(path: &Path) -> Result<GameSetup, GameSetupError> {
+        let serialized = fs::read_to_string(path).map_err(|source| GameSetupError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let game_setup: GameSetup =
+            ron::from_str(&serialized).map_err(|error| GameSetupError::Parse {
+                path: path.to_path_buf(),
+                message: error.to_string(),
+            })?;
+        game_setup.validate(path)?;
+        Ok(game_setup)
+    }
+
+    /// Rules have to be valid and players have to be there, with unique names
+    /// (map `player` properties refer to them) and at most one viewer
+    fn validate(&self, path: &Path) -> Result<(), GameSetupError> {
+        self.rules
+            .validate()
+            .map_err(|source| GameSetupError::Rules {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        if self.players.is_empty() {
+            return Err(GameSetupError::NoPlayers {
+                path: path.to_path_buf(),
+            });
+        }
+        for (index, player) in self.players.iter().enumerate() {
+            if self.players[..index]
+                .iter()
+                .any(|other| other.name == player.name)
+            {
+                return Err(GameSetupError::DuplicatePlayer {
+                    path: path.to_path_buf(),
+                    name: player.name.clone(),
+                });
+            }
+        }
+        let viewers = self
+            .players
+            .iter()
+            .filter(|player| player.viewer)
+            .map(|player| player.name.clone())
+            .collect::<Vec<_>>();
+        if viewers.len() > 1 {
+            return Err(GameSetupError::SeveralViewers {
+                path: path.to_path_buf(),
+                names: viewers,
+            });
+        }
+        Ok(())
+    }
+
+    /// Setup file referenced by the map, None if map doesn't have one
+    pub fn from_map(
+        map: &tiled::Map,
+        map_path: &Path,
+    ) -> Option<Result<GameSetup, GameSetupError>> {
+        let setup_file = match map.properties.get("setup")? {
+            PropertyValue::FileValue(file) | PropertyValue::StringValue(file) => file,
+            _ => return None,
+        };
+        let setup_path = map_path.parent().map_or_else(
+            || PathBuf::from(setup_file),
+            |parent| parent.join(setup_file),
+        );
+        Some(GameSetup::read(&setup_path))
+    }
+
+    pub fn game_rng(&self) -> GameRng {
+        GameRng::new(self.seed.unwrap_or_else(rand::random))
+    }
+
+    fn viewer_index(&self) -> usize {
+        self.players
+            .iter()
+            .position(|player| player.viewer)
+            .unwrap_or(0)
+    }
+
+    /// Spawn players with their stockpiles and units as children of the world.
+    /// Returns player entities in the same order as in setup.
+    pub fn spawn_players(
+        &self,
+        commands: &mut Commands,
+        game_ids: &mut GameIds,
+        world_entity: Entity,
+    ) -> Vec<Entity> {
+        let viewer_index = self.viewer_index();
+        self.players
+            .iter()
+            .enumerate()
+            .map(|(index, player_setup)| {
+                let mut player = commands.spawn_bundle(PlayerBundle {
+                    id: game_ids.next(),
+                    color: PlayerColor(player_setup.color),
+                    name: PlayerName(player_setup.name.clone()),
+                    ..Default::default()
+                });
+                let player_entity = player.id();
+                player.with_children(|builder| {
+                    for (resource, amount) in &player_setup.stockpiles {
+                        builder.spawn_bundle(PlayerStockpileBundle {
+                            player: OfPlayer(player_entity),
+                            resource: *resource,
+                            amount: StockpileResourceAmount(*amount),
+                        });
+                    }
+                    for resource in CapacityResourceType::iter() {
+                        builder.spawn_bundle(PlayerCapacityBundle {
+                            player: OfPlayer(player_entity),
+                            resource,
+                            ..Default::default()
+                        });
+                    }
+                    for (unit_type, position) in &player_setup.units {
+                        let mut unit = builder.spawn();
+                        UnitBundle::insert_full(
+                            &mut unit,
+                            game_ids.next(),
+                            player_entity,
+                            *unit_type,
+                            *position,
+                        );
+                    }
+                });
+                if index == viewer_index {
+                    player.insert_bundle(ui::ViewerBundle {
+                        ..Default::default()
+                    });
+                }
+                commands.entity(world_entity).add_child(player_entity);
+                player_entity
+            })
+            .collect()
+    }
+
+    /// Index of the player that owns site or province at start, named by its
+    /// `player` property on the map. They are neutral otherwise. Unknown
+    /// names are reported by `LoadedMap::player_problems`.
+    pub fn named_owner(&self, player_property: Option<&str>) -> Option<usize> {
+        let player_name = player_property?;
+        self.players
+            .iter()
+            .position(|player| player.name == player_name)
+    }
+
+    /// Index of the player that owns city at start, None if city is neutral
+    pub fn city_owner(&self, city_name: &str, player_property: Option<&str>) -> Option<usize> {
+        if let Some(player_name) = player_property {
+            return self
+                .players
+                .iter()
+                .position(|player| player.name == player_name);
+        }
+        self.players
+            .iter()
+            .position(|player| match &player.cities {
+                StartingCities::Named(names) => names.iter().any(|name| name == city_name),
+                _ => false,
+            })
+            .or_else(|| {
+                self.players
+                    .iter()
+                    .position(|player| matches!(player.cities, StartingCities::Rest))
+            })
+    }
+}
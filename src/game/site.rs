@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::EntityCommands;
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumIter, EnumString};
+
+use crate::{
+    game::{
+        map::Position,
+        province::InProvince,
+        world::{self, CapacityResourceType, GameId, StockpileResourceType},
+    },
+    prelude::*,
+};
+
+/// Single tile place on the map that isn't a city, read from `Sites` layer
+/// of the map
+#[derive(Component, Debug, Default)]
+pub struct Site {}
+
+#[derive(
+    Component,
+    Clone,
+    Copy,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    EnumString,
+    EnumIter,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+pub enum SiteType {
+    #[default]
+    Ruins,
+    SunNode,
+    ArcanaNode,
+    DeathNode,
+    ChaosNode,
+    NatureNode,
+    GoldMine,
+    LumberCamp,
+    MonsterLair,
+}
+
+impl SiteType {
+    pub fn get_site_stats(&self) -> SiteStats {
+        let mana_node = |resource| SiteStats {
+            site_type: *self,
+            base_stockpile_prosumers: HashMap::new(),
+            base_capacity_prosumers: HashMap::from([(resource, 2)]),
+        };
+        match self {
+            SiteType::Ruins | SiteType::MonsterLair => SiteStats {
+                site_type: *self,
+                base_stockpile_prosumers: HashMap::new(),
+                base_capacity_prosumers: HashMap::new(),
+            },
+            SiteType::SunNode => mana_node(CapacityResourceType::Sun),
+            SiteType::ArcanaNode => mana_node(CapacityResourceType::Arcana),
+            SiteType::DeathNode => mana_node(CapacityResourceType::Death),
+            SiteType::ChaosNode => mana_node(CapacityResourceType::Chaos),
+            SiteType::NatureNode => mana_node(CapacityResourceType::Nature),
+            SiteType::GoldMine => SiteStats {
+                site_type: *self,
+                base_stockpile_prosumers: HashMap::from([(StockpileResourceType::Gold, 10.)]),
+                base_capacity_prosumers: HashMap::new(),
+            },
+            SiteType::LumberCamp => SiteStats {
+                site_type: *self,
+                base_stockpile_prosumers: HashMap::from([(StockpileResourceType::Wood, 10.)]),
+                base_capacity_prosumers: HashMap::new(),
+            },
+        }
+    }
+}
+
+#[derive(Bundle, Debug, Default)]
+pub struct SiteBundle {
+    pub site: Site,
+    pub id: GameId,
+    pub province: InProvince,
+    pub position: Position,
+    pub site_type: SiteType,
+}
+
+impl SiteBundle {
+    pub fn new_site(
+        entity: &mut EntityCommands,
+        id: GameId,
+        player_option: Option<Entity>,
+        site_type: SiteType,
+        province: Entity,
+        position: Position,
+    ) -> Entity {
+        entity.insert_bundle(SiteBundle {
+            id,
+            province: InProvince(province),
+            position,
+            site_type,
+            ..Default::default()
+        });
+        if let Some(player_entity) = player_option {
+            set_site_owner(entity, site_type, player_entity);
+        }
+        entity.id()
+    }
+}
+
+/// Give site to the player, spawning prosumers of the site for them. Site
+/// should not have an owner already, prosumers of previous owner need to be
+/// despawned first.
+pub fn set_site_owner(entity: &mut EntityCommands, site_type: SiteType, player_entity: Entity) {
+    let site_stats = site_type.get_site_stats();
+    world::set_owner_with_prosumers(
+        entity,
+        &site_stats.base_stockpile_prosumers,
+        &site_stats.base_capacity_prosumers,
+        player_entity,
+    );
+}
+
+#[derive(Debug)]
+pub struct SiteStats {
+    pub site_type: SiteType,
+    pub base_stockpile_prosumers: HashMap<StockpileResourceType, f32>,
+    pub base_capacity_prosumers: HashMap<CapacityResourceType, i32>,
+}
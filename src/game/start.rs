@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{
+    game::{
+        load_map::MapPath,
+        replay::{self, RecordReplay, Replay},
+        save::{LoadGame, SaveGame, SaveGameError},
+        setup::{GameSetup, GameSetupError},
+        world::{Player, PlayerName},
+        GameDay, GameSpeed, GameTickTimer, InGameState,
+    },
+    options::GameOptions,
+    prelude::*,
+};
+
+#[derive(Debug, Error)]
+pub enum StartError {
+    #[error("{0}")]
+    Save(#[from] SaveGameError),
+    #[error("{0}")]
+    Setup(#[from] GameSetupError),
+    #[error("{option} only applies to new games, {} already has it", .path.display())]
+    OnlyForNewGames { option: &'static str, path: PathBuf },
+}
+
+/// Insert resources that make the game start the way options say: from a
+/// map, save or replay, with given setup and recorded unless replaying
+pub fn insert_start_resources(app: &mut App, options: &GameOptions) -> Result<(), StartError> {
+    let is_new_game = options.path.as_ref().map_or(true, |path| {
+        path.extension().map_or(true, |ext| ext != "ron")
+    });
+    // Saves and replays carry their own random state and players
+    let new_game_options = [
+        ("--seed", options.seed.is_some()),
+        ("--setup", options.setup.is_some()),
+    ];
+    if let Some((option, _)) = new_game_options
+        .into_iter()
+        .find(|(_, is_given)| !is_new_game && *is_given)
+    {
+        return Err(StartError::OnlyForNewGames {
+            option,
+            path: options.path.clone().unwrap_or_default(),
+        });
+    }
+    let is_replay = match &options.path {
+        Some(path) if replay::is_replay_path(path) => {
+            Replay::read(path)?.insert_into(app);
+            true
+        }
+        Some(path) if path.extension().map_or(false, |ext| ext == "ron") => {
+            app.insert_resource(LoadGame(SaveGame::read(path)?));
+            false
+        }
+        Some(path) => {
+            app.insert_resource(MapPath(path.clone()));
+            false
+        }
+        None => false,
+    };
+    if let Some(setup_path) = &options.setup {
+        app.insert_resource(GameSetup::read(setup_path)?);
+    }
+    if !is_replay && !options.no_record {
+        app.insert_resource(RecordReplay(replay::REPLAY_PATH.into()));
+    }
+    Ok(())
+}
+
+/// Move the viewer to the player options ask for, once players are spawned
+pub fn view_as_player(
+    mut commands: Commands,
+    options_option: Option<Res<GameOptions>>,
+    viewer_query: Query<Entity, With<ui::Viewer>>,
+    player_query: Query<(Entity, &PlayerName), With<Player>>,
+) {
+    let player_name = match options_option
+        .as_ref()
+        .and_then(|options| options.view_as.as_ref())
+    {
+        Some(player_name) => player_name,
+        None => return,
+    };
+    let player_entity = match player_query
+        .iter()
+        .find(|(_, PlayerName(name))| name == player_name)
+    {
+        Some((player_entity, _)) => player_entity,
+        None => {
+            warn!("There is no player {} to view as", player_name);
+            return;
+        }
+    };
+    for viewer_entity in viewer_query.iter() {
+        commands
+            .entity(viewer_entity)
+            .remove_bundle::<ui::ViewerBundle>();
+    }
+    commands
+        .entity(player_entity)
+        .insert_bundle(ui::ViewerBundle::default());
+}
+
+/// Game runs at max speed until this day, then goes back to normal
+pub struct SkipToDay(pub u32);
+
+pub fn start_game(
+    mut commands: Commands,
+    options_option: Option<Res<GameOptions>>,
+    mut game_tick_timer: ResMut<GameTickTimer>,
+) {
+    let options = match options_option {
+        Some(options) => options,
+        None => return,
+    };
+    if let Some(day) = options.skip_to_day {
+        game_tick_timer.set_rate(GameSpeed::Max.tick_rate());
+        // Days are shown counting from 1
+        commands.insert_resource(SkipToDay(day.saturating_sub(1)));
+        commands.insert_resource(NextState(InGameState::Running));
+    } else if options.running {
+        commands.insert_resource(NextState(InGameState::Running));
+    }
+}
+
+pub fn skip_to_day(
+    mut commands: Commands,
+    skip_to_day: Res<SkipToDay>,
+    options_option: Option<Res<GameOptions>>,
+    game_speed: Res<GameSpeed>,
+    mut game_tick_timer: ResMut<GameTickTimer>,
+    game_day_query: Query<&GameDay>,
+) {
+    if game_day_query.single().0 < skip_to_day.0 {
+        return;
+    }
+    game_tick_timer.set_rate(game_speed.tick_rate());
+    commands.remove_resource::<SkipToDay>();
+    if !options_option.map_or(false, |options| options.running) {
+        commands.insert_resource(NextState(InGameState::Paused));
+    }
+}
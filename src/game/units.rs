@@ -1,10 +1,19 @@
 use std::collections::HashMap;
 
 use bevy::ecs::{query::QueryItem, system::EntityCommands};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString};
 
 use crate::{
-    game::{map, map::Position, world, world::OfPlayer, GameTick},
+    game::{
+        map,
+        map::{MapTerrain, Position},
+        movement::{MovementRules, MovementType, MOVE_PROGRESS},
+        navigation, world,
+        world::{GameId, OfPlayer},
+        GameTick,
+    },
     prelude::*,
 };
 
@@ -14,7 +23,7 @@ pub struct UnitFigure {
 }
 
 #[derive(Component, Debug)]
-pub struct UnitFigureHealth(u32);
+pub struct UnitFigureHealth(pub u32);
 
 #[derive(Bundle, Debug)]
 pub struct UnitFigureBundle {
@@ -36,6 +45,7 @@ impl UnitFigureBundle {
 #[derive(Bundle, Debug, Default)]
 pub struct UnitBundle {
     pub unit: Unit,
+    pub id: GameId,
     pub unit_type: UnitType,
     pub position: map::Position,
     pub player: OfPlayer,
@@ -45,25 +55,44 @@ pub struct UnitBundle {
 impl UnitBundle {
     pub fn insert_full(
         entity: &mut EntityCommands,
+        id: GameId,
+        player_entity: Entity,
+        unit_type: UnitType,
+        position: map::Position,
+    ) -> Entity {
+        let unit_stats = unit_type.get_unit_stats();
+        let figures = (0..unit_stats.max_figures)
+            .map(|index| (index, unit_stats.max_health))
+            .collect::<Vec<_>>();
+        UnitBundle::insert_with_figures(entity, id, player_entity, unit_type, position, &figures)
+    }
+
+    /// Insert unit with only given figures, as (index, health), eg when some
+    /// of them were already lost
+    pub fn insert_with_figures(
+        entity: &mut EntityCommands,
+        id: GameId,
         player_entity: Entity,
         unit_type: UnitType,
         position: map::Position,
+        figures: &[(usize, u32)],
     ) -> Entity {
         let unit_stats = unit_type.get_unit_stats();
         entity
             .insert_bundle(UnitBundle {
                 unit: Unit {},
+                id,
                 unit_type,
                 position,
                 player: OfPlayer(player_entity),
                 ..Default::default()
             })
             .with_children(|unit| {
-                for index in 0..unit_stats.max_figures {
+                for (index, health) in figures {
                     unit.spawn().insert_bundle(UnitFigureBundle::new(
                         unit_type,
-                        index,
-                        UnitFigureHealth(unit_stats.max_health),
+                        *index,
+                        UnitFigureHealth(*health),
                     ));
                 }
                 for (resource, amount) in &unit_stats.capacity_cost {
@@ -83,7 +112,21 @@ impl UnitBundle {
 #[derive(Component, Debug, Default)]
 pub struct Unit {}
 
-#[derive(Component, Clone, Copy, Default, Debug, EnumString, EnumIter, Display)]
+#[derive(
+    Component,
+    Clone,
+    Copy,
+    Default,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    EnumIter,
+    Display,
+    Serialize,
+    Deserialize,
+)]
 pub enum UnitType {
     #[default]
     Skeleton,
@@ -95,7 +138,12 @@ pub enum UnitType {
 pub struct UnitStats {
     pub max_figures: usize,
     pub max_health: u32,
+    // damage every figure deals per tick in combat
+    pub damage: u32,
+    pub movement_type: MovementType,
     pub cost: HashMap<world::StockpileResourceType, f32>,
+    // ticks it takes to recruit the unit in a city
+    pub recruit_ticks: u32,
     pub capacity_cost: HashMap<world::CapacityResourceType, i32>,
 }
 
@@ -105,26 +153,35 @@ impl UnitType {
             UnitType::Skeleton => UnitStats {
                 max_figures: 4,
                 max_health: 4,
+                damage: 1,
+                movement_type: MovementType::Foot,
                 cost: HashMap::from([(world::StockpileResourceType::Gold, 100.)]),
+                recruit_ticks: 10,
                 capacity_cost: HashMap::from([(world::CapacityResourceType::Death, -1)]),
             },
             UnitType::DeathKnight => UnitStats {
                 max_figures: 2,
                 max_health: 10,
+                damage: 3,
+                movement_type: MovementType::Mounted,
                 cost: HashMap::from([(world::StockpileResourceType::Gold, 200.)]),
+                recruit_ticks: 20,
                 capacity_cost: HashMap::from([(world::CapacityResourceType::Death, -1)]),
             },
             UnitType::GiantSpider => UnitStats {
                 max_figures: 1,
                 max_health: 20,
+                damage: 4,
+                movement_type: MovementType::Climbing,
                 cost: HashMap::from([(world::StockpileResourceType::Gold, 500.)]),
+                recruit_ticks: 30,
                 capacity_cost: HashMap::from([(world::CapacityResourceType::Death, -1)]),
             },
         }
     }
 }
 
-#[derive(Component, Debug, Default)]
+#[derive(Component, Debug, Default, Clone, Hash, Serialize, Deserialize)]
 pub struct UnitOrders {
     orders: Vec<UnitOrder>,
 }
@@ -170,64 +227,139 @@ impl UnitOrders {
             self.orders.remove(0);
         }
     }
+
+    /// Positions unit is planning to move through, starting from the one it is moving to now
+    pub fn planned_path(&self, position: &Position) -> Vec<Position> {
+        let mut path = Vec::new();
+        for order in self.orders.iter() {
+            match order {
+                UnitOrder::Move { move_direction, .. } => {
+                    let mut next_position = *position;
+                    next_position.move_to_direction(move_direction);
+                    path.push(next_position);
+                }
+                UnitOrder::MoveToPosition {
+                    path: planned_path, ..
+                } => {
+                    path.extend(planned_path.iter().copied());
+                    break;
+                }
+            }
+        }
+        path
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub enum UnitOrder {
     Move {
         move_direction: Direction,
         // 1-100
         progress: u32,
+        // Ticks spent on the move, progress per tick depends on unit and terrain
+        #[serde(default)]
+        ticks: u32,
     },
     MoveToPosition {
         target_position: Position,
+        // planned steps to target, excluding current position, empty if not planned yet
+        path: Vec<Position>,
     },
 }
 
 impl UnitOrder {
+    pub fn move_to_position(target_position: Position) -> UnitOrder {
+        UnitOrder::MoveToPosition {
+            target_position,
+            path: Vec::new(),
+        }
+    }
+
     pub fn is_interruptable(&self) -> bool {
         match self {
-            &UnitOrder::Move { progress, .. } => progress <= 25,
+            // Units can still turn back during the first tick of the step
+            &UnitOrder::Move { ticks, .. } => ticks <= 1,
             UnitOrder::MoveToPosition { .. } => true,
         }
     }
 }
 
-type UnitOrdersQuery = (&'static mut UnitOrders, &'static mut Position);
+type UnitOrdersQuery = (
+    &'static mut UnitOrders,
+    &'static mut Position,
+    &'static UnitType,
+);
 
 pub fn unit_orders(
-    game_tick_query: Query<ChangeTrackers<GameTick>>,
+    game_tick_query: Query<(ChangeTrackers<GameTick>, &MapTerrain)>,
     mut unit_orders_query: Query<UnitOrdersQuery, With<Unit>>,
 ) {
-    let game_tick_change_tracker = game_tick_query.single();
+    let (game_tick_change_tracker, map_terrain) = game_tick_query.single();
     if game_tick_change_tracker.is_changed() {
-        unit_orders_query.for_each_mut(process_unit_orders)
+        let movement_rules: HashMap<MovementType, MovementRules> = MovementType::iter()
+            .map(|movement_type| (movement_type, movement_type.get_movement_rules()))
+            .collect();
+        unit_orders_query
+            .for_each_mut(|unit_item| process_unit_orders(map_terrain, &movement_rules, unit_item))
     }
 }
 
-fn process_unit_orders((mut unit_orders, mut position): QueryItem<UnitOrdersQuery>) {
+fn process_unit_orders(
+    map_terrain: &MapTerrain,
+    movement_rules: &HashMap<MovementType, MovementRules>,
+    (mut unit_orders, mut position, unit_type): QueryItem<UnitOrdersQuery>,
+) {
+    let rules = &movement_rules[&unit_type.get_unit_stats().movement_type];
     while let Some(mut next_order) = unit_orders.next_order() {
         match next_order {
             UnitOrder::Move {
                 move_direction,
                 ref mut progress,
+                ref mut ticks,
             } => {
-                *progress += 25;
-                if *progress >= 100 {
-                    position.move_to_direction(&move_direction);
-                } else {
-                    unit_orders.insert_order(next_order);
+                let progress_per_tick = map_terrain
+                    .neighbor(&position, &move_direction)
+                    .and_then(|next_position| map_terrain.get(&next_position))
+                    .and_then(|(base, top)| rules.progress_per_tick(base, top));
+                // Tile can't be entered anymore, drop the move so that path is re-planned
+                if let Some(progress_per_tick) = progress_per_tick {
+                    *progress += progress_per_tick;
+                    *ticks += 1;
+                    if *progress >= MOVE_PROGRESS {
+                        position.move_to_direction(&move_direction);
+                    } else {
+                        unit_orders.insert_order(next_order);
+                    }
+                    break;
                 }
-                break;
             }
-            UnitOrder::MoveToPosition { target_position } => {
-                if *position != target_position {
-                    unit_orders.insert_order(next_order);
-                    unit_orders.insert_order(UnitOrder::Move {
-                        move_direction: position.direction_to(&target_position),
-                        progress: 0,
-                    });
+            UnitOrder::MoveToPosition {
+                target_position,
+                ref mut path,
+            } => {
+                if *position == target_position {
+                    continue;
+                }
+                // Re-plan if we haven't yet or if the next step got blocked
+                let next_step_is_valid = path.first().map_or(false, |next_position| {
+                    navigation::can_step(map_terrain, rules, &position, next_position)
+                });
+                if !next_step_is_valid {
+                    *path = navigation::find_path(map_terrain, rules, &position, &target_position)
+                        .unwrap_or_default();
+                }
+                // Target can't be reached, drop the order
+                if path.is_empty() {
+                    continue;
                 }
+                let next_position = path.remove(0);
+                let move_direction = position.direction_to(&next_position);
+                unit_orders.insert_order(next_order);
+                unit_orders.insert_order(UnitOrder::Move {
+                    move_direction,
+                    progress: 0,
+                    ticks: 0,
+                });
             }
         }
     }
@@ -1,9 +1,47 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::EntityCommands;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 use strum_macros::{EnumIter, EnumString};
 
 use crate::prelude::*;
+
 #[derive(Component, Debug, Default)]
 pub struct Player {}
 
+/// Id of a player, city or unit that stays the same between runs, saves and
+/// replays, unlike `Entity` which depends on everything else that spawned.
+/// Also used to process entities in the same order every run.
+#[derive(
+    Component,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub struct GameId(pub u64);
+
+/// Hands out `GameId`s in order of spawning
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GameIds {
+    next: u64,
+}
+
+impl GameIds {
+    pub fn next(&mut self) -> GameId {
+        self.next += 1;
+        GameId(self.next)
+    }
+}
+
 #[derive(Component, Debug, Default)]
 pub struct PlayerName(pub String);
 
@@ -13,8 +51,10 @@ pub struct PlayerColor(pub Color);
 #[derive(Bundle, Debug, Default)]
 pub struct PlayerBundle {
     pub player: Player,
+    pub id: GameId,
     pub name: PlayerName,
     pub color: PlayerColor,
+    pub ledger: PlayerLedger,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
 }
@@ -39,7 +79,22 @@ pub struct PlayerStockpileBundle {
 #[derive(Component, Debug)]
 pub struct StockpileResourceAmount(pub f32);
 
-#[derive(Component, Debug, Clone, Copy, EnumIter, EnumString, Eq, PartialEq, Default, Hash)]
+#[derive(
+    Component,
+    Debug,
+    Clone,
+    Copy,
+    EnumIter,
+    EnumString,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Default,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
 pub enum StockpileResourceType {
     #[default]
     Gold,
@@ -56,13 +111,37 @@ pub struct StockpileResourceProsumerBundle {
     pub player: OfPlayer,
 }
 
-#[derive(Bundle, Debug)]
+#[derive(Bundle, Debug, Default)]
 pub struct PlayerCapacityBundle {
     pub player: OfPlayer,
     pub resource: CapacityResourceType,
+    pub amount: CapacityResourceAmount,
+}
+
+/// Capacity of the player, recalculated from prosumers every tick. Free
+/// goes negative when player has more units than the capacity allows.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityResourceAmount {
+    pub free: i32,
+    pub total: i32,
 }
 
-#[derive(Component, Debug, Clone, Copy, EnumIter, EnumString, Eq, PartialEq, Default, Hash)]
+#[derive(
+    Component,
+    Debug,
+    Clone,
+    Copy,
+    EnumIter,
+    EnumString,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Default,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
 pub enum CapacityResourceType {
     #[default]
     Sun,
@@ -81,3 +160,181 @@ pub struct CapacityResourceProsumerBundle {
     pub prosumer: CapacityResourceProsumer,
     pub player: OfPlayer,
 }
+
+/// Give city or site to the player, spawning its prosumers for them as its
+/// children. It should not have an owner already, prosumers of previous
+/// owner need to be despawned first.
+pub fn set_owner_with_prosumers(
+    entity: &mut EntityCommands,
+    stockpile_prosumers: &HashMap<StockpileResourceType, f32>,
+    capacity_prosumers: &HashMap<CapacityResourceType, i32>,
+    player_entity: Entity,
+) {
+    entity
+        .insert(OfPlayer(player_entity))
+        .with_children(|builder| {
+            for (resource, amount) in stockpile_prosumers {
+                builder
+                    .spawn()
+                    .insert_bundle(StockpileResourceProsumerBundle {
+                        player: OfPlayer(player_entity),
+                        resource: *resource,
+                        prosumer: StockpileResourceProsumer(*amount),
+                    });
+            }
+            for (resource, amount) in capacity_prosumers {
+                builder
+                    .spawn()
+                    .insert_bundle(CapacityResourceProsumerBundle {
+                        player: OfPlayer(player_entity),
+                        resource: *resource,
+                        prosumer: CapacityResourceProsumer(*amount),
+                    });
+            }
+        });
+}
+
+/// Economy of a player, rebuilt from stockpiles and prosumers every frame so
+/// that GUI, tooltips and AI can read it without summing things up themselves
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct PlayerLedger {
+    pub stockpiles: HashMap<StockpileResourceType, StockpileLedger>,
+    pub capacities: HashMap<CapacityResourceType, CapacityLedger>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StockpileLedger {
+    pub amount: f32,
+    // Per day
+    pub income: f32,
+    pub expenses: f32,
+    // Entity that has the prosumer (city, unit) and what it gives or takes
+    pub sources: Vec<(Entity, f32)>,
+}
+
+impl StockpileLedger {
+    pub fn balance(&self) -> f32 {
+        self.income - self.expenses
+    }
+}
+
+/// Amount is the player's `CapacityResourceAmount`, the one recruitment and
+/// disbanding use, sources only break it down
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CapacityLedger {
+    pub amount: CapacityResourceAmount,
+    pub sources: Vec<(Entity, i32)>,
+}
+
+impl CapacityLedger {
+    pub fn free(&self) -> i32 {
+        self.amount.free
+    }
+
+    pub fn total(&self) -> i32 {
+        self.amount.total
+    }
+
+    pub fn used(&self) -> i32 {
+        self.amount.total - self.amount.free
+    }
+}
+
+impl PlayerLedger {
+    fn empty() -> PlayerLedger {
+        PlayerLedger {
+            stockpiles: StockpileResourceType::iter()
+                .map(|resource| (resource, StockpileLedger::default()))
+                .collect(),
+            capacities: CapacityResourceType::iter()
+                .map(|resource| (resource, CapacityLedger::default()))
+                .collect(),
+        }
+    }
+}
+
+type StockpileProsumerQuery = (
+    Entity,
+    Option<&'static Parent>,
+    &'static OfPlayer,
+    &'static StockpileResourceType,
+    &'static StockpileResourceProsumer,
+);
+
+type CapacityProsumerQuery = (
+    Entity,
+    Option<&'static Parent>,
+    &'static OfPlayer,
+    &'static CapacityResourceType,
+    &'static CapacityResourceProsumer,
+);
+
+pub fn update_player_ledgers(
+    mut player_query: Query<(Entity, &mut PlayerLedger), With<Player>>,
+    stockpile_query: Query<(&OfPlayer, &StockpileResourceType, &StockpileResourceAmount)>,
+    stockpile_prosumer_query: Query<StockpileProsumerQuery>,
+    capacity_query: Query<(&OfPlayer, &CapacityResourceType, &CapacityResourceAmount)>,
+    capacity_prosumer_query: Query<CapacityProsumerQuery>,
+) {
+    let mut ledgers: HashMap<Entity, PlayerLedger> = player_query
+        .iter()
+        .map(|(player, _)| (player, PlayerLedger::empty()))
+        .collect();
+
+    for (OfPlayer(player), resource, StockpileResourceAmount(amount)) in stockpile_query.iter() {
+        if let Some(ledger) = ledgers.get_mut(player) {
+            ledger.stockpiles.entry(*resource).or_default().amount += amount;
+        }
+    }
+
+    for (entity, parent, OfPlayer(player), resource, StockpileResourceProsumer(amount)) in
+        stockpile_prosumer_query.iter()
+    {
+        if let Some(ledger) = ledgers.get_mut(player) {
+            let stockpile = ledger.stockpiles.entry(*resource).or_default();
+            if *amount >= 0. {
+                stockpile.income += amount;
+            } else {
+                stockpile.expenses -= amount;
+            }
+            // Prosumers are children of the thing that produces or consumes
+            let source = parent.map_or(entity, |Parent(parent)| *parent);
+            stockpile.sources.push((source, *amount));
+        }
+    }
+
+    for (OfPlayer(player), resource, capacity_amount) in capacity_query.iter() {
+        if let Some(ledger) = ledgers.get_mut(player) {
+            ledger.capacities.entry(*resource).or_default().amount = *capacity_amount;
+        }
+    }
+
+    for (entity, parent, OfPlayer(player), resource, CapacityResourceProsumer(amount)) in
+        capacity_prosumer_query.iter()
+    {
+        if let Some(ledger) = ledgers.get_mut(player) {
+            let capacity = ledger.capacities.entry(*resource).or_default();
+            let source = parent.map_or(entity, |Parent(parent)| *parent);
+            capacity.sources.push((source, *amount));
+        }
+    }
+
+    for (player, mut ledger) in player_query.iter_mut() {
+        if let Some(mut new_ledger) = ledgers.remove(&player) {
+            // Stable order, so that ledger only changes when economy changes
+            for stockpile in new_ledger.stockpiles.values_mut() {
+                stockpile
+                    .sources
+                    .sort_unstable_by(|(left, left_amount), (right, right_amount)| {
+                        left.cmp(right).then(left_amount.total_cmp(right_amount))
+                    });
+            }
+            for capacity in new_ledger.capacities.values_mut() {
+                capacity.sources.sort_unstable();
+            }
+            if *ledger != new_ledger {
+                *ledger = new_ledger;
+            }
+        }
+    }
+}
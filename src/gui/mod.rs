@@ -15,6 +15,8 @@ impl Plugin for GuiPlugin {
             .add_enter_system(config::EngineState::LoadingGraphics, setup_egui)
             .add_plugin(plugins::CursorPlugin {})
             .add_plugin(plugins::DebugTooltipPlugin {})
+            .add_plugin(plugins::ErrorScreenPlugin {})
+            .add_plugin(plugins::ProvinceInfoPlugin {})
             .add_plugin(plugins::TitleBarPlugin {})
             .add_plugin(plugins::TimeBarPlugin {})
             .add_plugin(plugins::ResourceBarPlugin {})
@@ -0,0 +1,56 @@
+use bevy::app::AppExit;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    config::{EngineState, UiSyncLabel},
+    game::load_map::{MapLoadError, MapLoadFailed},
+    prelude::*,
+};
+
+pub struct ErrorScreenPlugin {}
+
+impl Plugin for ErrorScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            config::Stage::UiSync,
+            ConditionSet::new()
+                .run_in_state(EngineState::LoadingFailed)
+                .label_and_after(UiSyncLabel::Update)
+                .with_system(error_screen)
+                .into(),
+        );
+    }
+}
+
+/// Gui textures aren't set up when loading fails, so this uses plain egui
+fn error_screen(
+    mut egui_context: ResMut<EguiContext>,
+    map_load_failed: Res<MapLoadFailed>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    egui::CentralPanel::default().show(egui_context.ctx_mut(), |ui| {
+        ui.heading("Couldn't load the map");
+        match &map_load_failed.0 {
+            MapLoadError::Tmx { .. } => {
+                ui.label(map_load_failed.0.to_string());
+            }
+            MapLoadError::Invalid { path, problems } => {
+                ui.label(format!(
+                    "{} has {} problems:",
+                    path.display(),
+                    problems.len()
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(ui.available_height() - 40.)
+                    .show(ui, |ui| {
+                        for problem in problems {
+                            ui.label(problem.to_string());
+                        }
+                    });
+            }
+        }
+        if ui.button("Exit").clicked() {
+            exit_events.send(AppExit);
+        }
+    });
+}
@@ -1,5 +1,7 @@
 mod cursor;
 mod debug_tooltip;
+mod error_screen;
+mod province_info;
 mod resource_bar;
 mod selected_window;
 mod time_bar;
@@ -8,6 +10,8 @@ mod unit_badge;
 
 pub use cursor::*;
 pub use debug_tooltip::*;
+pub use error_screen::*;
+pub use province_info::*;
 pub use resource_bar::*;
 pub use selected_window::*;
 pub use time_bar::*;
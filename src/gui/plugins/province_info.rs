@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    config::{EngineState, UiSyncLabel},
+    game::{
+        map::{Terrain, TerrainBase},
+        province::{City, CityType, Province},
+        site::{Site, SiteType},
+        world::{OfPlayer, Player, PlayerName},
+    },
+    gui::{
+        gui_context::{GuiContext, TextureType},
+        widgets::*,
+    },
+    prelude::*,
+    ui::{CursorPosition, Viewer, ViewerMap},
+};
+
+pub struct ProvinceInfoPlugin {}
+
+impl Plugin for ProvinceInfoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            config::Stage::UiSync,
+            ConditionSet::new()
+                .run_in_state(EngineState::InGame)
+                .label_and_after(UiSyncLabel::Update)
+                .with_system(province_info)
+                .into(),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn province_info(
+    mut egui_context: ResMut<EguiContext>,
+    gui_context: Res<GuiContext>,
+    viewer_query: Query<(&ViewerMap, &CursorPosition), With<Viewer>>,
+    province_query: Query<(&Province, Option<&OfPlayer>, Option<&Children>)>,
+    terrain_query: Query<&TerrainBase, With<Terrain>>,
+    city_query: Query<(&CityType, Option<&OfPlayer>), With<City>>,
+    site_query: Query<(&SiteType, Option<&OfPlayer>), With<Site>>,
+    player_query: Query<&PlayerName, With<Player>>,
+) {
+    let hovered = viewer_query
+        .get_single()
+        .ok()
+        .and_then(|(viewer_map, cursor_position)| {
+            viewer_map.province_at_position(&cursor_position.exact_position_option?)
+        });
+    let province_entity = match hovered {
+        Some(province_entity) => province_entity,
+        None => return,
+    };
+    let (province, owner_option, children_option) = match province_query.get(province_entity) {
+        Ok(province) => province,
+        Err(_) => return,
+    };
+    let player_name = |owner_option: Option<&OfPlayer>| {
+        owner_option
+            .and_then(|OfPlayer(player)| player_query.get(*player).ok())
+            .map_or_else(|| "Neutral".to_string(), |PlayerName(name)| name.clone())
+    };
+    let children = children_option
+        .map(|children| children.iter().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let mut terrain_counts = BTreeMap::new();
+    for TerrainBase(terrain_type) in children
+        .iter()
+        .filter_map(|child| terrain_query.get(*child).ok())
+    {
+        *terrain_counts.entry(*terrain_type).or_insert(0) += 1;
+    }
+    let tile_count = terrain_counts.values().sum::<u32>().max(1);
+    let mut terrain_counts = terrain_counts.into_iter().collect::<Vec<_>>();
+    terrain_counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    NinePatchWindow::new(
+        egui::RichText::new(&province.name).text_style(egui::TextStyle::Name("Heading2".into())),
+    )
+    .fixed_size(egui::vec2(240., 160.))
+    .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(4., -4.))
+    .title_bar_nine_patch(
+        *gui_context
+            .get_texture_id(TextureType::Window, "dark")
+            .unwrap(),
+        egui::vec2(32., 32.),
+    )
+    .body_nine_patch(
+        *gui_context
+            .get_texture_id(TextureType::Window, "bright")
+            .unwrap(),
+        egui::vec2(32., 32.),
+    )
+    .frame(
+        egui::Frame::window(&egui_context.ctx_mut().style())
+            .inner_margin(egui::style::Margin::symmetric(8., 0.)),
+    )
+    .show(egui_context.ctx_mut(), |ui| {
+        if let Some(region) = &province.region {
+            ui.label(format!("Region: {}", region));
+        }
+        ui.label(format!("Owner: {}", player_name(owner_option)));
+        ui.label(
+            terrain_counts
+                .iter()
+                .map(|(terrain_type, count)| {
+                    format!("{:?} {}%", terrain_type, count * 100 / tile_count)
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        for (city_type, city_owner) in children
+            .iter()
+            .filter_map(|child| city_query.get(*child).ok())
+        {
+            ui.label(format!(
+                "City {:?} ({})",
+                city_type,
+                player_name(city_owner)
+            ));
+        }
+        for (site_type, site_owner) in children
+            .iter()
+            .filter_map(|child| site_query.get(*child).ok())
+        {
+            ui.label(format!(
+                "Site {:?} ({})",
+                site_type,
+                player_name(site_owner)
+            ));
+        }
+    });
+}
@@ -1,4 +1,3 @@
-use assoc::AssocExt;
 use bevy_egui::{egui, EguiContext};
 
 use crate::{
@@ -43,7 +42,7 @@ fn setup_resource_bar(mut commands: Commands) {
 struct PlayerStockpileResource {
     pub amount: f32,
     pub income: f32,
-    // Tooltip stuff here maybe? could be separate tyfpe
+    pub expenses: f32,
 }
 
 impl Eq for PlayerStockpileResource {}
@@ -71,6 +70,7 @@ impl Default for PlayerResources {
                     PlayerStockpileResource {
                         amount: 0.0,
                         income: 0.0,
+                        expenses: 0.0,
                     },
                 ),
                 (
@@ -78,6 +78,7 @@ impl Default for PlayerResources {
                     PlayerStockpileResource {
                         amount: 0.0,
                         income: 0.0,
+                        expenses: 0.0,
                     },
                 ),
             ],
@@ -109,67 +110,21 @@ impl Default for PlayerResources {
 
 fn bind_current_player_resources(
     mut player_resources: ResMut<PlayerResources>,
-    stockpile_resources_query: Query<(
-        &game::world::StockpileResourceType,
-        &game::world::StockpileResourceAmount,
-    )>,
-    stockile_resources_prosumer_query: Query<(
-        &game::world::StockpileResourceType,
-        &game::world::StockpileResourceProsumer,
-    )>,
-    capacity_resources_query: Query<&game::world::CapacityResourceType>,
-    capacity_resources_prosumer_query: Query<(
-        &game::world::CapacityResourceType,
-        &game::world::CapacityResourceProsumer,
-    )>,
+    viewer_query: Query<&game::world::PlayerLedger, With<ui::Viewer>>,
 ) {
-    for (resource_type, game::world::StockpileResourceAmount(amount)) in
-        stockpile_resources_query.iter()
-    {
-        player_resources
-            .stockpile_resources
-            .entry(*resource_type)
-            .and_modify(|res| {
-                res.amount = *amount;
-                res.income = 0.0;
-            });
-    }
-
-    for (resource, game::world::StockpileResourceProsumer(amount)) in
-        stockile_resources_prosumer_query.iter()
-    {
-        player_resources
-            .stockpile_resources
-            .entry(*resource)
-            .and_modify(|res| {
-                res.income += amount;
-            });
-    }
-
-    for resource_type in capacity_resources_query.iter() {
-        player_resources
-            .capacity_resources
-            .entry(*resource_type)
-            .and_modify(|res| {
-                res.free = 0;
-                res.total = 0;
-            });
+    let ledger = viewer_query.single();
+    for (resource_type, res) in player_resources.stockpile_resources.iter_mut() {
+        if let Some(stockpile) = ledger.stockpiles.get(resource_type) {
+            res.amount = stockpile.amount;
+            res.income = stockpile.income;
+            res.expenses = stockpile.expenses;
+        }
     }
-
-    for (resource, game::world::CapacityResourceProsumer(amount)) in
-        capacity_resources_prosumer_query.iter()
-    {
-        let amount_value = *amount;
-
-        player_resources
-            .capacity_resources
-            .entry(*resource)
-            .and_modify(|res| {
-                if amount_value >= 0 {
-                    res.total += amount_value;
-                }
-                res.free += amount_value;
-            });
+    for (resource_type, res) in player_resources.capacity_resources.iter_mut() {
+        if let Some(capacity) = ledger.capacities.get(resource_type) {
+            res.free = capacity.free();
+            res.total = capacity.total();
+        }
     }
 }
 
@@ -199,19 +154,29 @@ fn resource_bar(
                         gui_context.icon_texture_id_for_stockpile_resource(resource_type),
                         egui::vec2(16., 16.),
                     );
-                    let income_text = if resource.income >= 0. {
-                        format!("+{:}", resource.income)
+                    let balance = resource.income - resource.expenses;
+                    let income_text = if balance >= 0. {
+                        format!("+{:}", balance)
                     } else {
-                        format!("-{:}", resource.income.abs())
+                        format!("-{:}", balance.abs())
                     };
-                    ui.label(format!("{:}{:}", resource.amount, income_text));
+                    ui.label(format!("{:}{:}", resource.amount, income_text))
+                        .on_hover_text(format!(
+                            "Income: {:}\nExpenses: {:}",
+                            resource.income, resource.expenses
+                        ));
                 }
                 for (resource_type, resource) in resources.capacity_resources.iter() {
                     ui.image(
                         gui_context.icon_texture_id_for_capacity_resource(resource_type),
                         egui::vec2(16., 16.),
                     );
-                    ui.label(format!("{:}/{:}", resource.free, resource.total));
+                    ui.label(format!("{:}/{:}", resource.free, resource.total))
+                        .on_hover_text(format!(
+                            "Used: {:}\nTotal: {:}",
+                            resource.total - resource.free,
+                            resource.total
+                        ));
                 }
             });
         });
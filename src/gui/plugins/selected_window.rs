@@ -3,15 +3,18 @@ use bevy_egui::{egui, EguiContext};
 use crate::{
     config::{EngineState, UiSyncLabel},
     game::{
+        player_commands::{GameCommand, PlayerCommandQueue},
         province::{City, CityType},
+        recruitment::RecruitmentQueue,
         units::{Unit, UnitType},
+        world::{GameId, OfPlayer},
     },
     gui::{
         gui_context::{GuiContext, TextureType},
         widgets::*,
     },
     prelude::*,
-    ui::{Selected, SelectedEntity, Viewer},
+    ui::{RecruitChoice, Selected, SelectedEntity, Viewer},
 };
 
 pub struct SelectedWindowPlugin {}
@@ -29,14 +32,23 @@ impl Plugin for SelectedWindowPlugin {
     }
 }
 
+type CityWindowQuery = (
+    &'static GameId,
+    &'static CityType,
+    Option<&'static OfPlayer>,
+    &'static RecruitmentQueue,
+);
+
 fn selected_window(
     mut egui_context: ResMut<EguiContext>,
     gui_context: Res<GuiContext>,
-    selection_query: Query<&Selected, With<Viewer>>,
+    selection_query: Query<(Entity, &GameId, &Selected), With<Viewer>>,
     unit_query: Query<&UnitType, With<Unit>>,
-    city_query: Query<&CityType, With<City>>,
+    city_query: Query<CityWindowQuery, With<City>>,
+    mut command_queue: ResMut<PlayerCommandQueue>,
+    mut recruit_choice: ResMut<RecruitChoice>,
 ) {
-    let Selected(selection) = selection_query.single();
+    let (viewer_entity, viewer_id, Selected(selection)) = selection_query.single();
     if !selection.is_empty() {
         NinePatchWindow::new(
             egui::RichText::new("Selected Units")
@@ -70,8 +82,48 @@ fn selected_window(
                         }
                     }
                     SelectedEntity::City(entity) => {
-                        if let Ok(city_type) = city_query.get(*entity) {
+                        if let Ok((city_id, city_type, player_option, recruitment)) =
+                            city_query.get(*entity)
+                        {
                             ui.label(format!("City: {:?}", city_type));
+                            for queued in recruitment.queue.iter() {
+                                ui.label(format!(
+                                    "Recruiting {}: {} ticks",
+                                    queued.unit_type, queued.ticks_left
+                                ));
+                            }
+                            if player_option != Some(&OfPlayer(viewer_entity)) {
+                                continue;
+                            }
+                            ui.horizontal(|ui| {
+                                for unit_type in city_type.get_city_stats().recruitable_units {
+                                    let cost = unit_type
+                                        .get_unit_stats()
+                                        .cost
+                                        .iter()
+                                        .map(|(resource, amount)| {
+                                            format!("{} {:?}", amount, resource)
+                                        })
+                                        .collect::<Vec<_>>()
+                                        .join(", ");
+                                    let recruit = ui.add(gui_context.button(
+                                        &gui::ButtonType::Shallow,
+                                        &gui::ButtonSize::Small,
+                                        &format!("{} ({})", unit_type, cost),
+                                    ));
+                                    if recruit.clicked() {
+                                        recruit_choice.0.insert(*entity, unit_type);
+                                        command_queue.push(
+                                            *viewer_id,
+                                            GameCommand::RecruitUnit {
+                                                city: *city_id,
+                                                unit_type,
+                                                rally_point: None,
+                                            },
+                                        );
+                                    }
+                                }
+                            });
                         }
                     }
                 }
@@ -1,9 +1,10 @@
 use bevy_egui::{egui, EguiContext};
 use leafwing_input_manager::prelude::ActionState;
+use strum::IntoEnumIterator;
 
 use crate::{
     config::{EngineState, UiSyncLabel},
-    game::{GameDay, GameTick, InGameState},
+    game::{GameDay, GameSpeed, GameTick, InGameState},
     gui::{
         gui_context::{GuiContext, TextureType},
         widgets::*,
@@ -31,6 +32,7 @@ fn time_bar(
     mut egui_context: ResMut<EguiContext>,
     gui_context: Res<GuiContext>,
     game_state: Res<CurrentState<InGameState>>,
+    game_speed: Res<GameSpeed>,
     game_time_query: Query<(&GameDay, &GameTick)>,
     mut input_action_query: Query<&mut ActionState<InputActions>>,
 ) {
@@ -87,6 +89,22 @@ fn time_bar(
                     let mut input_action = input_action_query.single_mut();
                     input_action.press(InputActions::Resume);
                 }
+
+                for speed in GameSpeed::iter() {
+                    let is_current = *game_speed == speed;
+                    let speed_button = ui.add_enabled(
+                        !is_current,
+                        gui_context.button(
+                            &gui::ButtonType::Shallow,
+                            &gui::ButtonSize::Small,
+                            speed.label(),
+                        ),
+                    );
+                    if speed_button.clicked() && !is_current {
+                        let mut input_action = input_action_query.single_mut();
+                        input_action.press(InputActions::for_speed(speed));
+                    }
+                }
             });
         });
 }
@@ -30,6 +30,7 @@ impl Plugin for UnitBadgePlugin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn unit_badges(
     windows: Res<Windows>,
     mut egui_context: ResMut<EguiContext>,
@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+
+use crate::{
+    game::{
+        checksum::StateChecksum,
+        load_map::{MapLoadError, MapLoadFailed, MapPath},
+        province::{City, Province},
+        units::Unit,
+        world::{OfPlayer, Player, PlayerName, StockpileResourceAmount, StockpileResourceType},
+        GameDay, GameTick, GameTickTimer, TickRate,
+    },
+    prelude::*,
+};
+
+/// Runs `GamePlugin` without window, assets, rendering or gui. Skips engine
+/// states that only do graphics work and ticks the game once per app update.
+pub struct HeadlessPlugin {}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameTickTimer::new(TickRate::EveryUpdate))
+            .add_system(proceed_to_next_state.run_in_state(config::EngineState::LoadingAssets))
+            .add_system(proceed_to_next_state.run_in_state(config::EngineState::LoadingGraphics))
+            .add_enter_system(config::EngineState::InGame, start_running);
+    }
+}
+
+fn proceed_to_next_state(mut commands: Commands, state: Res<CurrentState<config::EngineState>>) {
+    commands.insert_resource(NextState(state.0.next()));
+}
+
+fn start_running(mut commands: Commands) {
+    commands.insert_resource(NextState(game::InGameState::Running));
+}
+
+pub fn build_app(map_path: Option<PathBuf>) -> App {
+    let mut app = App::new();
+    app.add_loopless_state(config::EngineState::LoadingAssets);
+
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(bevy::input::InputPlugin)
+        .add_plugin(game::GamePlugin {})
+        .add_plugin(HeadlessPlugin {});
+
+    if let Some(map_path) = map_path {
+        app.insert_resource(MapPath(map_path));
+    }
+    app
+}
+
+/// Update the app until the world is loaded and game is running, or until
+/// the map fails to load
+pub fn run_until_in_game(app: &mut App) -> Result<(), MapLoadError> {
+    while !is_running(&app.world) {
+        app.update();
+        if let Some(MapLoadFailed(error)) = app.world.remove_resource::<MapLoadFailed>() {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Advance the simulation by exactly `ticks` game ticks. Game has to be
+/// running already, see `run_until_in_game`.
+pub fn run_ticks(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+/// Advance the simulation until `days` more days have passed. Game has to be
+/// running already, see `run_until_in_game`.
+pub fn run_days(app: &mut App, days: u32) {
+    let target_day = current_time(&mut app.world).0 + days;
+    while current_time(&mut app.world).0 < target_day {
+        app.update();
+    }
+}
+
+fn is_running(world: &World) -> bool {
+    matches!(
+        world.get_resource::<CurrentState<game::InGameState>>(),
+        Some(CurrentState(game::InGameState::Running))
+    )
+}
+
+fn current_time(world: &mut World) -> (u32, usize) {
+    world
+        .query::<(&GameDay, &GameTick)>()
+        .iter(world)
+        .next()
+        .map(|(GameDay(day), GameTick(tick))| (*day, *tick))
+        .unwrap_or((0, 0))
+}
+
+#[derive(Debug)]
+pub struct SimulationSummary {
+    pub day: u32,
+    pub tick: usize,
+    /// Checksum of the last tick, zero before the first one
+    pub checksum: u64,
+    pub players: Vec<PlayerSummary>,
+}
+
+#[derive(Debug)]
+pub struct PlayerSummary {
+    pub name: String,
+    pub stockpiles: Vec<(StockpileResourceType, f32)>,
+    pub units: usize,
+    pub cities: usize,
+    pub provinces: usize,
+}
+
+impl SimulationSummary {
+    pub fn from_world(world: &mut World) -> SimulationSummary {
+        let (day, tick) = current_time(world);
+        let mut players: Vec<(Entity, PlayerSummary)> = world
+            .query_filtered::<(Entity, &PlayerName), With<Player>>()
+            .iter(world)
+            .map(|(entity, PlayerName(name))| {
+                (
+                    entity,
+                    PlayerSummary {
+                        name: name.clone(),
+                        stockpiles: Vec::new(),
+                        units: 0,
+                        cities: 0,
+                        provinces: 0,
+                    },
+                )
+            })
+            .collect();
+
+        for (OfPlayer(player), resource_type, StockpileResourceAmount(amount)) in world
+            .query::<(&OfPlayer, &StockpileResourceType, &StockpileResourceAmount)>()
+            .iter(world)
+        {
+            if let Some((_, summary)) = players.iter_mut().find(|(entity, _)| entity == player) {
+                summary.stockpiles.push((*resource_type, *amount));
+            }
+        }
+
+        for OfPlayer(player) in world.query_filtered::<&OfPlayer, With<Unit>>().iter(world) {
+            if let Some((_, summary)) = players.iter_mut().find(|(entity, _)| entity == player) {
+                summary.units += 1;
+            }
+        }
+
+        for OfPlayer(player) in world.query_filtered::<&OfPlayer, With<City>>().iter(world) {
+            if let Some((_, summary)) = players.iter_mut().find(|(entity, _)| entity == player) {
+                summary.cities += 1;
+            }
+        }
+
+        for OfPlayer(player) in world
+            .query_filtered::<&OfPlayer, With<Province>>()
+            .iter(world)
+        {
+            if let Some((_, summary)) = players.iter_mut().find(|(entity, _)| entity == player) {
+                summary.provinces += 1;
+            }
+        }
+
+        let checksum = world
+            .get_resource::<StateChecksum>()
+            .map_or(0, |state_checksum| state_checksum.checksum);
+
+        SimulationSummary {
+            day,
+            tick,
+            checksum,
+            players: players.into_iter().map(|(_, summary)| summary).collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for SimulationSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Day {:04} Tick {:02} checksum {:016x}",
+            self.day + 1,
+            self.tick + 1,
+            self.checksum
+        )?;
+        for player in &self.players {
+            write!(
+                f,
+                "{}: {} units, {} cities, {} provinces",
+                player.name, player.units, player.cities, player.provinces
+            )?;
+            for (resource_type, amount) in &player.stockpiles {
+                write!(f, ", {:?} {}", resource_type, amount)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@ pub mod assets;
 pub mod config;
 pub mod game;
 pub mod gui;
+pub mod headless;
+pub mod options;
 pub mod prelude;
 pub mod render;
 pub mod ui;
@@ -1,23 +1,32 @@
 use bevy::asset::AssetServerSettings;
-use lands_of_mana::prelude::*;
+use clap::{CommandFactory, ErrorKind, Parser};
+use lands_of_mana::{
+    game::start::{self, StartError},
+    options::GameOptions,
+    prelude::*,
+};
 
+/// Run with `--help` to see options. Game is recorded to
+/// `saves/last_game.replay.ron` unless it's a replay or `--no-record` is given.
 fn main() {
-    let window = WindowDescriptor {
-        mode: bevy::window::WindowMode::BorderlessFullscreen,
-        title: String::from("mom4x"),
-        // width: 1600.,
-        // height: 1000.,
-        ..Default::default()
-    };
+    let options = GameOptions::parse();
 
     let mut app = App::new();
+    if let Err(error) = start::insert_start_resources(&mut app, &options) {
+        let kind = match error {
+            StartError::OnlyForNewGames { .. } => ErrorKind::ArgumentConflict,
+            _ => ErrorKind::ValueValidation,
+        };
+        GameOptions::command().error(kind, error).exit();
+    }
 
-    app.insert_resource(window)
+    app.insert_resource(options.window_descriptor())
         .insert_resource(AssetServerSettings {
             asset_folder: "assets/export".to_string(),
             watch_for_changes: true,
         })
         .insert_resource(Msaa { samples: 1 })
+        .insert_resource(options)
         .add_loopless_state(config::EngineState::LoadingAssets);
 
     app.add_plugins(DefaultPlugins)
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::prelude::*;
+
+/// Options game is started with, parsed from command line. Inserted as a
+/// resource before plugins are added, so that they can use it.
+#[derive(Parser, Debug, Clone)]
+#[clap(name = "lands_of_mana")]
+pub struct GameOptions {
+    /// Map (.tmx), save (.ron) or replay (.replay.ron) to start from
+    pub path: Option<PathBuf>,
+    /// Game setup (.ron) to use instead of the one the map points to
+    #[clap(long)]
+    pub setup: Option<PathBuf>,
+    /// Seed of the game random number generator for new games
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// Run in a window of given size, eg 1600x1000, instead of fullscreen
+    #[clap(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size)]
+    pub windowed: Option<(f32, f32)>,
+    /// Unpause the game as soon as it's loaded
+    #[clap(long)]
+    pub running: bool,
+    /// Name of the player to view the game as
+    #[clap(long, value_name = "PLAYER")]
+    pub view_as: Option<String>,
+    /// Simulate the game at max speed until given day starts, for testing
+    #[clap(long, value_name = "DAY")]
+    pub skip_to_day: Option<u32>,
+    /// Camera zoom at start, from 3 to 10
+    #[clap(long, default_value_t = 5, value_parser = clap::value_parser!(i32).range(3..=10))]
+    pub zoom: i32,
+    /// Don't record the game to a replay
+    #[clap(long)]
+    pub no_record: bool,
+    /// Rebuild the map in place when map file changes on disk
+    #[clap(long)]
+    pub dev: bool,
+}
+
+fn parse_window_size(size: &str) -> Result<(f32, f32), String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {}", size))?;
+    let parse = |value: &str| match value.parse::<f32>() {
+        Ok(value) if value > 0. && value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("invalid window size {}: has to be positive", size)),
+        Err(error) => Err(format!("invalid window size {}: {}", size, error)),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+impl GameOptions {
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        let window = WindowDescriptor {
+            title: String::from("mom4x"),
+            ..Default::default()
+        };
+        match self.windowed {
+            Some((width, height)) => WindowDescriptor {
+                mode: bevy::window::WindowMode::Windowed,
+                width,
+                height,
+                ..window
+            },
+            None => WindowDescriptor {
+                mode: bevy::window::WindowMode::BorderlessFullscreen,
+                ..window
+            },
+        }
+    }
+}
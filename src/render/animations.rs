@@ -2,9 +2,12 @@ use benimator;
 use bevy::ecs::query::QueryItem;
 
 use crate::{
-    game::units::{Unit, UnitFigure, UnitOrder, UnitOrders, UnitType},
+    game::{
+        combat::BattleEvent,
+        units::{Unit, UnitFigure, UnitOrder, UnitOrders, UnitType},
+    },
     prelude::*,
-    render::units::{FigureAnimationType, UnitSprite},
+    render::units::{figure_animation_duration, FigureAnimationType, UnitSprite},
 };
 
 pub struct AnimationsRenderPlugin {}
@@ -21,6 +24,8 @@ impl Plugin for AnimationsRenderPlugin {
                     .with_system(run_new_unit_animations)
                     .with_system(run_unit_animations)
                     .with_system(unit_animations_for_order)
+                    .with_system(battle_animations)
+                    .with_system(finish_battle_animations)
                     .into(),
             );
     }
@@ -68,16 +73,20 @@ fn run_new_unit_animations(
     });
 }
 
+fn animation_type_for_orders(orders: &UnitOrders) -> FigureAnimationType {
+    match orders.peek_order() {
+        Some(UnitOrder::Move { .. }) => FigureAnimationType::Walk,
+        Some(UnitOrder::MoveToPosition { .. }) => FigureAnimationType::Walk,
+        _ => FigureAnimationType::Idle,
+    }
+}
+
 fn unit_animations_for_order(
     unit_query: Query<(&UnitOrders, &Children), (With<Unit>, Changed<UnitOrders>)>,
     mut animated_figures_query: Query<&mut Animation, With<UnitFigure>>,
 ) {
     for (orders, children) in unit_query.iter() {
-        let new_animation_type = match orders.peek_order() {
-            Some(UnitOrder::Move { .. }) => FigureAnimationType::Walk,
-            Some(UnitOrder::MoveToPosition { .. }) => FigureAnimationType::Walk,
-            _ => FigureAnimationType::Idle,
-        };
+        let new_animation_type = animation_type_for_orders(orders);
         for child in children.iter() {
             if let Ok(mut animation) = animated_figures_query.get_mut(*child) {
                 let Animation::FigureAnimation {
@@ -89,6 +98,57 @@ fn unit_animations_for_order(
     }
 }
 
+/// Figure is playing battle animation, after which it goes back to animation
+/// for unit orders
+#[derive(Component, Debug)]
+struct BattleAnimation(Timer);
+
+fn battle_animations(
+    mut commands: Commands,
+    mut battle_events: EventReader<BattleEvent>,
+    mut animated_figures_query: Query<&mut Animation, With<UnitFigure>>,
+) {
+    for event in battle_events.iter() {
+        let (figure, new_animation_type) = match event {
+            BattleEvent::Attack { figure } => (figure, FigureAnimationType::Attack),
+            BattleEvent::Hit { figure, .. } => (figure, FigureAnimationType::Hit),
+            _ => continue,
+        };
+        if let Ok(mut animation) = animated_figures_query.get_mut(*figure) {
+            let Animation::FigureAnimation {
+                ref mut animation_type,
+            } = animation.as_mut();
+            *animation_type = new_animation_type;
+            commands.entity(*figure).insert(BattleAnimation(Timer::new(
+                figure_animation_duration(),
+                false,
+            )));
+        }
+    }
+}
+
+fn finish_battle_animations(
+    mut commands: Commands,
+    time: Res<Time>,
+    unit_query: Query<&UnitOrders, With<Unit>>,
+    mut animated_figures_query: Query<
+        (Entity, &Parent, &mut BattleAnimation, &mut Animation),
+        With<UnitFigure>,
+    >,
+) {
+    for (entity, parent, mut battle_animation, mut animation) in animated_figures_query.iter_mut() {
+        if battle_animation.0.tick(time.delta()).finished() {
+            if let Ok(orders) = unit_query.get(parent.0) {
+                let Animation::FigureAnimation {
+                    ref mut animation_type,
+                } = animation.as_mut();
+                *animation_type = animation_type_for_orders(orders);
+            }
+            commands.entity(entity).remove::<BattleAnimation>();
+        }
+    }
+}
+
 fn run_unit_animations(
     mut commands: Commands,
     mut animations: ResMut<Assets<benimator::SpriteSheetAnimation>>,
@@ -27,6 +27,23 @@ impl Plugin for RenderPlugin {
         .add_plugin(units::RenderUnitsPlugin {})
         .add_plugin(animations::AnimationsRenderPlugin {})
         .add_enter_system(config::EngineState::LoadingGraphics, tilemap::setup)
+        .add_system_set_to_stage(
+            config::Stage::UiSync,
+            ConditionSet::new()
+                .run_in_state(config::EngineState::InGame)
+                .run_on_event::<game::load_map::MapReloaded>()
+                .label_and_after(config::UiSyncLabel::Sync)
+                .with_system(tilemap::rebuild)
+                .into(),
+        )
+        .add_system_set_to_stage(
+            config::Stage::UiSync,
+            ConditionSet::new()
+                .run_in_state(config::EngineState::InGame)
+                .label_and_after(config::UiSyncLabel::Sync)
+                .with_system(tilemap::update_borders)
+                .into(),
+        )
         .add_system(proceed_to_ready_state.run_in_state(config::EngineState::LoadingGraphics));
     }
 }
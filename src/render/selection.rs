@@ -6,12 +6,15 @@ use strum_macros::{EnumIter, EnumString};
 
 use crate::{
     config::Direction,
-    game::units::UnitOrders,
+    game::{
+        map::{Map, Position},
+        units::{Unit, UnitOrders},
+    },
     prelude::*,
     render::z_level::ZLevel,
     ui::{
         camera, CursorDragSelect, CursorDragSelectType, CursorSelectionTarget, CursorTargetTime,
-        Selectable, Selected, Viewer,
+        Selectable, Selected, SelectedEntity, Viewer,
     },
 };
 
@@ -21,6 +24,7 @@ impl Plugin for RenderSelectionPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ShapePlugin)
             .add_enter_system(config::EngineState::LoadingGraphics, setup_drag_selection)
+            .add_enter_system(config::EngineState::LoadingGraphics, setup_unit_paths)
             .add_system_set_to_stage(
                 config::Stage::UiSync,
                 ConditionSet::new()
@@ -37,6 +41,7 @@ impl Plugin for RenderSelectionPlugin {
                     .run_in_state(config::EngineState::InGame)
                     .with_system(run_update_selection_box)
                     .with_system(update_drag_selection)
+                    .with_system(update_unit_paths)
                     .into(),
             );
     }
@@ -345,6 +350,63 @@ fn update_drag_selection(
     }
 }
 
+#[derive(Component, Debug, Default)]
+pub struct UnitPaths {}
+
+fn setup_unit_paths(mut commands: Commands, viewer_query: Query<Entity, With<Viewer>>) {
+    let viewer = viewer_query.single();
+    commands.entity(viewer).with_children(|builder| {
+        builder
+            .spawn_bundle(ShapeBundle {
+                visibility: Visibility { is_visible: false },
+                ..GeometryBuilder::new().build(
+                    DrawMode::Stroke(StrokeMode::new(Color::WHITE, 1.0)),
+                    Transform::from_translation(Vec3::new(0., 0., ZLevel::OrderDirections.into())),
+                )
+            })
+            .insert(UnitPaths::default());
+    });
+}
+
+// Draw planned paths of selected units
+fn update_unit_paths(
+    map_query: Query<&Map>,
+    viewer_query: Query<(&Selected, ChangeTrackers<Selected>), With<Viewer>>,
+    units_query: Query<(&Position, &UnitOrders), With<Unit>>,
+    changed_units_query: Query<(), (With<Unit>, Or<(Changed<Position>, Changed<UnitOrders>)>)>,
+    mut unit_paths_query: Query<(&mut Path, &mut Visibility), With<UnitPaths>>,
+) {
+    let (Selected(selection), selection_tracker) = viewer_query.single();
+    if !selection_tracker.is_changed() && changed_units_query.is_empty() {
+        return;
+    }
+    let map = map_query.single();
+    let (mut path, mut visibility) = unit_paths_query.single_mut();
+    let tile_center = Vec2::new(8., 8.);
+    let mut shape_path = ShapePath::new();
+    let mut has_paths = false;
+    for selected_entity in selection.entities() {
+        if let SelectedEntity::Unit(unit_entity) = selected_entity {
+            if let Ok((position, unit_orders)) = units_query.get(*unit_entity) {
+                let planned_path = unit_orders.planned_path(position);
+                if !planned_path.is_empty() {
+                    has_paths = true;
+                    let points = std::iter::once(position)
+                        .chain(planned_path.iter())
+                        .map(|position| map.position_to_pixel_position(position) + tile_center)
+                        .collect();
+                    shape_path = shape_path.add(&Polygon {
+                        points,
+                        closed: false,
+                    });
+                }
+            }
+        }
+    }
+    *path = shape_path.build();
+    visibility.is_visible = has_paths;
+}
+
 #[derive(Component, Debug)]
 pub struct WithDirectionIndicator {
     pub indicator: Entity,
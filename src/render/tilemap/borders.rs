@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use bevy_ecs_tilemap::tiles::{TileBundle, TileColor, TilePos2d, TileTexture, TileVisible};
+use strum::IntoEnumIterator;
+
+use super::layers;
+use crate::{
+    game::{
+        map::Position,
+        province::{Province, ProvinceOwnerChanged},
+        world::{OfPlayer, PlayerColor},
+    },
+    prelude::*,
+    ui::{BorderMode, CursorPosition, Viewer, ViewerMap},
+};
+
+/// Tile on the `Borders` layer, drawn on sides of the province tile where
+/// other province is
+#[derive(Component, Debug)]
+pub struct BorderTile {
+    pub province: Entity,
+}
+
+const NEUTRAL_BORDER_COLOR: Color = Color::rgba(0.8, 0.8, 0.8, 0.6);
+
+/// Border tiles for every terrain tile that has a different province next
+/// to it, as children of the terrain tile
+pub fn build_borders(
+    commands: &mut Commands,
+    tilemap_layer_manager: &mut layers::TilemapLayerManager,
+    map: &game::map::Map,
+    terrain: &[(Entity, Position, Entity)],
+) {
+    let province_at = terrain
+        .iter()
+        .map(|(_, position, province)| (*position, *province))
+        .collect::<HashMap<_, _>>();
+    for (entity, position, province) in terrain.iter() {
+        let sides = DirectionSides::iter()
+            .filter(|side| {
+                position
+                    .neighbor(&side_direction(side), map.width, map.height)
+                    .and_then(|neighbor| province_at.get(&neighbor))
+                    .map_or(false, |neighbor_province| neighbor_province != province)
+            })
+            .collect::<Vec<_>>();
+        if sides.is_empty() {
+            continue;
+        }
+        let tile_pos = TilePos2d {
+            x: position.x,
+            y: position.y,
+        };
+        let mut border_tile = None;
+        commands.entity(*entity).with_children(|builder| {
+            border_tile = Some(tilemap_layer_manager.insert_tile_bundle(
+                builder,
+                &layers::TilemapLayerType::Borders,
+                &tile_pos,
+                TileBundle {
+                    position: tile_pos,
+                    texture: TileTexture(get_border_texture_id(&sides)),
+                    color: TileColor(NEUTRAL_BORDER_COLOR),
+                    ..Default::default()
+                },
+            ));
+        });
+        if let Some(border_tile) = border_tile {
+            commands.entity(border_tile).insert(BorderTile {
+                province: *province,
+            });
+        }
+    }
+}
+
+fn side_direction(side: &DirectionSides) -> Direction {
+    match side {
+        DirectionSides::North => Direction::North,
+        DirectionSides::East => Direction::East,
+        DirectionSides::South => Direction::South,
+        DirectionSides::West => Direction::West,
+    }
+}
+
+/// Borders are in a row of `fog_of_war_and_map.png`, indexed by which sides
+/// have a border (north 1, east 2, south 4, west 8)
+fn get_border_texture_id(sides: &[DirectionSides]) -> u32 {
+    96 + sides
+        .iter()
+        .map(|side| match side {
+            DirectionSides::North => 1,
+            DirectionSides::East => 2,
+            DirectionSides::South => 4,
+            DirectionSides::West => 8,
+        })
+        .sum::<u32>()
+}
+
+/// Color borders by the owner of the province and hide ones that shouldn't
+/// be seen in hover mode. Only does work when a `ProvinceOwnerChanged` event
+/// comes, or border mode or the hovered province change.
+#[allow(clippy::too_many_arguments)]
+pub fn update_borders(
+    border_mode: Res<BorderMode>,
+    mut hovered_province: Local<Option<Entity>>,
+    viewer_query: Query<(&ViewerMap, &CursorPosition), With<Viewer>>,
+    province_query: Query<Option<&OfPlayer>, With<Province>>,
+    mut owner_events: EventReader<ProvinceOwnerChanged>,
+    added_border_query: Query<(), Added<BorderTile>>,
+    player_query: Query<&PlayerColor>,
+    mut border_query: Query<(&BorderTile, &mut TileColor, &mut TileVisible)>,
+) {
+    let hovered = viewer_query
+        .get_single()
+        .ok()
+        .and_then(|(viewer_map, cursor_position)| {
+            viewer_map.province_at_position(&cursor_position.exact_position_option?)
+        });
+    let hover_changed = *border_mode == BorderMode::Hover && hovered != *hovered_province;
+    *hovered_province = hovered;
+    let owners_changed = owner_events.iter().count() > 0;
+    if !border_mode.is_changed()
+        && !hover_changed
+        && !owners_changed
+        && added_border_query.is_empty()
+    {
+        return;
+    }
+
+    for (BorderTile { province }, mut tile_color, mut tile_visible) in border_query.iter_mut() {
+        let color = match province_query.get(*province) {
+            Ok(Some(OfPlayer(owner))) => player_query
+                .get(*owner)
+                .map_or(NEUTRAL_BORDER_COLOR, |PlayerColor(color)| *color),
+            _ => NEUTRAL_BORDER_COLOR,
+        };
+        let visible = match *border_mode {
+            BorderMode::Always => true,
+            BorderMode::Hover => hovered == Some(*province),
+        };
+        tile_color.0 = color;
+        tile_visible.0 = visible;
+    }
+}
@@ -51,7 +51,7 @@ impl TilemapLayer {
         let storage = Tile2dStorage::empty(size);
         let layer = LayerInner {
             size,
-            entity: child_builder.spawn().id(),
+            entity: child_builder.spawn().insert(super::MapTilemap).id(),
             storage,
             z,
         };
@@ -108,7 +108,7 @@ impl TilemapLayer {
         builder: &mut ChildBuilder,
         tile_pos: &TilePos2d,
         tile_bundle: TileBundle,
-    ) {
+    ) -> Entity {
         let layer = self.get_layer_mut();
         let tile_entity = builder
             .spawn()
@@ -118,6 +118,7 @@ impl TilemapLayer {
             })
             .id();
         layer.storage.set(tile_pos, Some(tile_entity));
+        tile_entity
     }
 
     fn default_bundle_params(self) -> (Entity, TilemapBundle) {
@@ -406,13 +407,13 @@ impl TilemapLayerManager {
         tilemap_layer_type: &TilemapLayerType,
         tile_pos: &TilePos2d,
         tile_bundle: TileBundle,
-    ) {
+    ) -> Entity {
         self.tilemap_layers
             .iter_mut()
             .find(|(layer_type, _)| layer_type == tilemap_layer_type)
             .unwrap()
             .1
-            .insert_tile_bundle(builder, tile_pos, tile_bundle);
+            .insert_tile_bundle(builder, tile_pos, tile_bundle)
     }
 
     pub fn insert_terrain_bundles(
@@ -2,39 +2,85 @@ use std::collections::HashMap;
 
 use bevy::prelude::*;
 use bevy_ecs_tilemap::{
-    map::Tilemap2dSize,
+    map::{Tilemap2dSize, TilemapId},
     tiles::{Tile2dStorage, TileBundle, TilePos2d, TileTexture},
 };
 
+mod borders;
 mod layers;
 mod tile_selection;
 
+pub use borders::{update_borders, BorderTile};
+pub use tile_selection::{get_base_forest_texture_id, get_base_mountain_texture_id};
+
 use crate::{
     assets,
     game::{
         self,
         map::{Position, TerrainBase, TerrainTop},
         province::{CityTileIndex, CityType},
+        site::SiteType,
     },
 };
 
+/// Marks tilemap layers and other entities that only hold tiles, so that
+/// they can be removed when map is rebuilt
+#[derive(Component, Debug, Default)]
+pub struct MapTilemap;
+
+/// Remove tiles and tilemap layers and build them again from the reloaded map
+#[allow(clippy::too_many_arguments)]
+pub fn rebuild(
+    mut commands: Commands,
+    tiles: ResMut<assets::TileAssets>,
+    tilemap_query: Query<Entity, With<MapTilemap>>,
+    tile_query: Query<Entity, With<TilemapId>>,
+    map_query: Query<(Entity, &game::map::Map)>,
+    terrain_query: Query<TerrainQuery>,
+    city_tile_query: Query<CityTileQuery>,
+    site_query: Query<SiteQuery>,
+) {
+    for entity in tilemap_query.iter().chain(tile_query.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    setup(
+        commands,
+        tiles,
+        map_query,
+        terrain_query,
+        city_tile_query,
+        site_query,
+    );
+}
+
+type TerrainQuery = (
+    Entity,
+    &'static game::map::Position,
+    &'static game::map::TerrainBase,
+    &'static game::map::TerrainTop,
+    &'static game::province::InProvince,
+);
+
+type CityTileQuery = (
+    Entity,
+    &'static game::map::Position,
+    &'static game::province::CityType,
+    &'static game::province::CityTileIndex,
+);
+
+type SiteQuery = (
+    Entity,
+    &'static game::map::Position,
+    &'static game::site::SiteType,
+);
+
 pub fn setup(
     mut commands: Commands,
     tiles: ResMut<assets::TileAssets>,
     map_query: Query<(Entity, &game::map::Map)>,
-    terrain_query: Query<(
-        Entity,
-        &game::map::Position,
-        &game::map::TerrainBase,
-        &game::map::TerrainTop,
-        Option<&game::map::ProvinceBorder>,
-    )>,
-    city_tile_query: Query<(
-        Entity,
-        &game::map::Position,
-        &game::province::CityType,
-        &game::province::CityTileIndex,
-    )>,
+    terrain_query: Query<TerrainQuery>,
+    city_tile_query: Query<CityTileQuery>,
+    site_query: Query<SiteQuery>,
 ) {
     let (game_world_entity, map) = map_query.single();
 
@@ -49,7 +95,7 @@ pub fn setup(
         layers::TilemapLayerManager::new(&mut commands.entity(game_world_entity), map);
 
     commands.entity(game_world_entity).with_children(|builder| {
-        builder.spawn().with_children(|builder| {
+        builder.spawn().insert(MapTilemap).with_children(|builder| {
             build_background(builder, &mut tilemap_layer_manager, &size);
         });
     });
@@ -63,9 +109,22 @@ pub fn setup(
         pos_to_terrain.insert(position, (terrain_type, *terrain_top));
     }
 
-    for (entity, position, TerrainBase(terrain_type), terrain_top, _border_option) in
-        terrain_query.iter()
-    {
+    let province_terrain = terrain_query
+        .iter()
+        .map(
+            |(entity, position, _, _, game::province::InProvince(province))| {
+                (entity, *position, *province)
+            },
+        )
+        .collect::<Vec<_>>();
+    borders::build_borders(
+        &mut commands,
+        &mut tilemap_layer_manager,
+        map,
+        &province_terrain,
+    );
+
+    for (entity, position, TerrainBase(terrain_type), terrain_top, _) in terrain_query.iter() {
         let tile_pos: TilePos2d = TilePos2d {
             x: position.x,
             y: position.y,
@@ -154,6 +213,18 @@ pub fn setup(
         });
     }
 
+    for (entity, position, site_type) in site_query.iter() {
+        commands.entity(entity).with_children(|builder| {
+            let site_tile = build_site_tile(position, site_type);
+            tilemap_layer_manager.insert_tile_bundle(
+                builder,
+                &layers::TilemapLayerType::Sites,
+                &site_tile.position,
+                site_tile,
+            );
+        });
+    }
+
     for (entity, tilemap_bundle, _tilemap_layer_type) in
         tilemap_layer_manager.drain_all_tilemaps_to_bundle(&tiles)
     {
@@ -380,3 +451,25 @@ fn build_city_tile(
         ..Default::default()
     }
 }
+
+fn build_site_tile(game_position: &Position, site_type: &SiteType) -> TileBundle {
+    let texture_id = match site_type {
+        SiteType::Ruins => 160,
+        SiteType::SunNode => 176,
+        SiteType::ArcanaNode => 177,
+        SiteType::DeathNode => 178,
+        SiteType::ChaosNode => 179,
+        SiteType::NatureNode => 180,
+        SiteType::GoldMine => 192,
+        SiteType::LumberCamp => 193,
+        SiteType::MonsterLair => 208,
+    };
+    TileBundle {
+        position: TilePos2d {
+            x: game_position.x,
+            y: game_position.y,
+        },
+        texture: TileTexture(texture_id),
+        ..Default::default()
+    }
+}
@@ -5,6 +5,7 @@ use strum_macros::{EnumIter, EnumString};
 
 use crate::{
     game::{
+        combat::BattleEvent,
         map::{Map, Position},
         units::{Unit, UnitFigure, UnitOrder, UnitOrders, UnitType},
     },
@@ -31,11 +32,21 @@ impl Plugin for RenderUnitsPlugin {
                 .label_and_after(config::UiSyncLabel::Update)
                 .run_in_state(config::EngineState::InGame)
                 .with_system(run_unit_position_to_transfors)
+                .with_system(run_dead_figures)
+                .with_system(despawn_dead_figures)
                 .into(),
         );
     }
 }
 
+const FIGURE_ANIMATION_FRAMES: usize = 4;
+const FIGURE_ANIMATION_FRAME_DURATION: Duration = Duration::from_millis(150);
+
+/// How long it takes to play all frames of figure animation once
+pub fn figure_animation_duration() -> Duration {
+    FIGURE_ANIMATION_FRAME_DURATION * FIGURE_ANIMATION_FRAMES as u32
+}
+
 pub trait UnitSprite {
     fn get_default_tile_index(&self) -> usize;
     fn get_animation_frames(&self, animation_type: &FigureAnimationType) -> Vec<benimator::Frame>;
@@ -54,8 +65,8 @@ impl UnitSprite for game::units::UnitType {
     fn get_animation_frames(&self, animation_type: &FigureAnimationType) -> Vec<benimator::Frame> {
         let start_tile = self.get_default_tile_index();
         let start_animation_tile = start_tile + *animation_type as usize;
-        (start_animation_tile..start_animation_tile + 4)
-            .map(|tile| benimator::Frame::new(tile, Duration::from_millis(150)))
+        (start_animation_tile..start_animation_tile + FIGURE_ANIMATION_FRAMES)
+            .map(|tile| benimator::Frame::new(tile, FIGURE_ANIMATION_FRAME_DURATION))
             .collect()
     }
 
@@ -156,6 +167,68 @@ pub fn run_new_figures_spritesheet(
     }
 }
 
+/// Figure that died in battle, shown until its death animation finishes
+#[derive(Component, Debug)]
+pub struct DeadFigure(Timer);
+
+pub fn run_dead_figures(
+    mut commands: Commands,
+    creatures: Res<assets::CreatureAssets>,
+    mut animations: ResMut<Assets<benimator::SpriteSheetAnimation>>,
+    map_query: Query<&Map>,
+    mut battle_events: EventReader<BattleEvent>,
+) {
+    let map = map_query.single();
+    for event in battle_events.iter() {
+        if let BattleEvent::FigureDied {
+            unit_type,
+            position,
+            index,
+        } = event
+        {
+            let figure_transform = unit_type
+                .get_figure_transforms()
+                .get(*index)
+                .copied()
+                .unwrap_or_else(Transform::identity);
+            let unit_translation = map
+                .position_to_pixel_position(position)
+                .extend(ZLevel::Units.into());
+            let animation = benimator::SpriteSheetAnimation::from_frames(
+                unit_type.get_animation_frames(&FigureAnimationType::Death),
+            )
+            .once();
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: unit_type.get_default_tile_index(),
+                        anchor: bevy::sprite::Anchor::BottomLeft,
+                        ..Default::default()
+                    },
+                    texture_atlas: creatures.creatures.clone(),
+                    transform: figure_transform
+                        .with_translation(unit_translation + figure_transform.translation),
+                    ..Default::default()
+                })
+                .insert(animations.add(animation))
+                .insert(benimator::Play)
+                .insert(DeadFigure(Timer::new(figure_animation_duration(), false)));
+        }
+    }
+}
+
+pub fn despawn_dead_figures(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dead_figure_query: Query<(Entity, &mut DeadFigure)>,
+) {
+    for (entity, mut dead_figure) in dead_figure_query.iter_mut() {
+        if dead_figure.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 pub fn set_unit_transform(
     commands: &mut Commands,
     map: &Map,
@@ -169,6 +242,7 @@ pub fn set_unit_transform(
             Some(UnitOrder::Move {
                 move_direction,
                 progress,
+                ..
             }) => {
                 let multiply =
                     std::cmp::max_by(2., 10. * (*progress as f32) / 100., |l, r| l.total_cmp(r));
@@ -3,6 +3,7 @@ use leafwing_input_manager::prelude::*;
 
 use crate::{
     config::{EngineState, Stage, UiSyncLabel, UpdateStageLabel},
+    options::GameOptions,
     prelude::*,
 };
 
@@ -86,13 +87,18 @@ pub fn ndc_to_world(camera: &Camera, camera_transform: &GlobalTransform, ndc: Ve
     ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
 }
 
-fn setup(mut commands: Commands, world_query: Query<Entity, With<game::GameWorld>>) {
+fn setup(
+    mut commands: Commands,
+    options_option: Option<Res<GameOptions>>,
+    world_query: Query<Entity, With<game::GameWorld>>,
+) {
+    let zoom = options_option.map_or(5, |options| options.zoom);
     commands
         .entity(world_query.single())
         .with_children(|builder| {
             builder.spawn_bundle(PixelCameraBundle::new(PixelProjection {
                 centered: true,
-                zoom: 5,
+                zoom,
                 ..Default::default()
             }));
         });
@@ -1,6 +1,7 @@
 use std::hash::Hash;
 
 use leafwing_input_manager::prelude::*;
+use strum::IntoEnumIterator;
 
 use crate::{prelude::*, ui::Viewer};
 
@@ -15,6 +16,16 @@ pub fn setup_input_map(mut commands: Commands, viewer_query: Query<Entity, With<
         (KeyCode::D, InputActions::CameraMoveEast),
         (KeyCode::Z, InputActions::CameraZoomIn),
         (KeyCode::X, InputActions::CameraZoomOut),
+        (KeyCode::F5, InputActions::QuickSave),
+        (KeyCode::B, InputActions::ToggleBorders),
+        // game speed
+        (KeyCode::Key1, InputActions::SpeedHalf),
+        (KeyCode::Key2, InputActions::SpeedNormal),
+        (KeyCode::Key3, InputActions::SpeedDouble),
+        (KeyCode::Key4, InputActions::SpeedQuadruple),
+        (KeyCode::Key5, InputActions::SpeedMax),
+        (KeyCode::Equals, InputActions::SpeedUp),
+        (KeyCode::Minus, InputActions::SlowDown),
     ]);
     input_map.insert(MouseButton::Left, InputActions::Select);
     input_map.insert(MouseButton::Right, InputActions::Contextual);
@@ -33,6 +44,18 @@ pub enum InputActions {
     Resume,
     TogglePause,
 
+    SpeedHalf,
+    SpeedNormal,
+    SpeedDouble,
+    SpeedQuadruple,
+    SpeedMax,
+    SpeedUp,
+    SlowDown,
+
+    QuickSave,
+
+    ToggleBorders,
+
     CameraMoveNorth,
     CameraMoveSouth,
     CameraMoveWest,
@@ -46,8 +69,21 @@ pub enum InputActions {
     Contextual,
 }
 
+impl InputActions {
+    pub fn for_speed(speed: game::GameSpeed) -> InputActions {
+        match speed {
+            game::GameSpeed::Half => InputActions::SpeedHalf,
+            game::GameSpeed::Normal => InputActions::SpeedNormal,
+            game::GameSpeed::Double => InputActions::SpeedDouble,
+            game::GameSpeed::Quadruple => InputActions::SpeedQuadruple,
+            game::GameSpeed::Max => InputActions::SpeedMax,
+        }
+    }
+}
+
 pub fn input_to_game_actions(
     game_state: Res<CurrentState<game::InGameState>>,
+    game_speed: Res<game::GameSpeed>,
     input_action_query: Query<&ActionState<InputActions>>,
     mut world_action_query: Query<&mut ActionState<game::actions::WorldActions>>,
 ) {
@@ -67,4 +103,44 @@ pub fn input_to_game_actions(
     {
         world_action_state.press(game::actions::WorldActions::Resume)
     }
+
+    for speed in game::GameSpeed::iter() {
+        if input_action_state.just_released(InputActions::for_speed(speed)) {
+            world_action_state.press(speed.world_action())
+        }
+    }
+    if input_action_state.just_released(InputActions::SpeedUp) {
+        world_action_state.press(game_speed.faster().world_action())
+    }
+    if input_action_state.just_released(InputActions::SlowDown) {
+        world_action_state.press(game_speed.slower().world_action())
+    }
+
+    if input_action_state.just_released(InputActions::QuickSave) {
+        world_action_state.press(game::actions::WorldActions::Save)
+    }
+}
+
+/// When province borders are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderMode {
+    #[default]
+    Always,
+    // Only borders of the province under the cursor
+    Hover,
+}
+
+pub fn toggle_border_mode(
+    input_action_query: Query<&ActionState<InputActions>>,
+    mut border_mode: ResMut<BorderMode>,
+) {
+    if input_action_query
+        .single()
+        .just_released(InputActions::ToggleBorders)
+    {
+        *border_mode = match *border_mode {
+            BorderMode::Always => BorderMode::Hover,
+            BorderMode::Hover => BorderMode::Always,
+        };
+    }
 }
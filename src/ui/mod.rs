@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use bevy_egui::EguiContext;
 use bevy_pixel_camera::PixelProjection;
 use leafwing_input_manager::prelude::*;
@@ -5,9 +7,13 @@ use leafwing_input_manager::prelude::*;
 use crate::{
     config::{EngineState, UpdateStageLabel},
     game::{
+        capacity::UnitDisbanded,
+        combat::BattleEvent,
         map::Position,
-        province::City,
-        units::{Unit, UnitOrder, UnitOrders},
+        player_commands::{GameCommand, PlayerCommandQueue},
+        province::{City, CityType},
+        units::{Unit, UnitType},
+        world::{GameId, OfPlayer},
     },
     prelude::*,
 };
@@ -24,7 +30,9 @@ pub struct InputPlugin {}
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(bevy::input::system::exit_on_esc_system)
+        app.init_resource::<BorderMode>()
+            .init_resource::<RecruitChoice>()
+            .add_system(bevy::input::system::exit_on_esc_system)
             .add_system(bevy::window::exit_on_window_close_system)
             .add_plugin(InputManagerPlugin::<InputActions>::default())
             .add_enter_system(config::EngineState::InGame, setup_input_map)
@@ -34,6 +42,7 @@ impl Plugin for InputPlugin {
                     .run_in_state(EngineState::InGame)
                     .with_system(add_new_entitites_viewer_map)
                     .with_system(remove_entities_from_viewer_map)
+                    .with_system(remove_destroyed_units_from_selection)
                     .with_system(update_position_on_viewer_map)
                     .with_system(cursor_position)
                     .into(),
@@ -43,6 +52,7 @@ impl Plugin for InputPlugin {
                     .label_and_after(UpdateStageLabel::Input)
                     .run_in_state(EngineState::InGame)
                     .with_system(input_to_game_actions)
+                    .with_system(toggle_border_mode)
                     .with_system(drag_selection)
                     .with_system(hover)
                     .with_system(select)
@@ -186,6 +196,11 @@ pub enum CursorKind {
     Settings,
 }
 
+/// Unit last recruited with a button in each city, right click with the city
+/// selected recruits it again
+#[derive(Debug, Default)]
+pub struct RecruitChoice(pub HashMap<Entity, UnitType>);
+
 #[derive(Component, Debug, Default)]
 pub struct CursorPixelPosition(Vec2);
 
@@ -384,25 +399,80 @@ fn select(
     }
 }
 
+fn remove_destroyed_units_from_selection(
+    mut viewer_query: Query<&mut Selected, With<Viewer>>,
+    mut battle_events: EventReader<BattleEvent>,
+    mut disband_events: EventReader<UnitDisbanded>,
+) {
+    let mut selected = viewer_query.single_mut();
+    let destroyed_units = battle_events
+        .iter()
+        .filter_map(|event| match event {
+            BattleEvent::UnitDestroyed { unit } => Some(*unit),
+            _ => None,
+        })
+        .chain(
+            disband_events
+                .iter()
+                .map(|UnitDisbanded { unit, .. }| *unit),
+        );
+    for unit in destroyed_units {
+        if selected.0.is_selected(unit) {
+            selected.0.remove_unit_from_selection(unit);
+        }
+    }
+}
+
 fn contextual(
     input_action_query: Query<&ActionState<InputActions>>,
-    viewer_query: Query<(&CursorPosition, &Selected), With<Viewer>>,
-    mut unit_orders_query: Query<&mut UnitOrders, With<Unit>>,
+    viewer_query: Query<(Entity, &GameId, &CursorPosition, &Selected), With<Viewer>>,
+    unit_query: Query<(&GameId, &OfPlayer), With<Unit>>,
+    city_query: Query<(&GameId, &OfPlayer, &CityType), With<City>>,
+    recruit_choice: Res<RecruitChoice>,
+    mut command_queue: ResMut<PlayerCommandQueue>,
 ) {
     let input_action_state = input_action_query.single();
     let just_released = input_action_state.just_released(InputActions::Contextual);
-    let (cursor_position, selected) = viewer_query.single();
+    let (viewer_entity, viewer_id, cursor_position, selected) = viewer_query.single();
     if just_released && cursor_position.exact_position_option.is_some() && !selected.0.is_empty() {
         for selected_entity in selected.0.entities() {
             match selected_entity {
                 SelectedEntity::Unit(entity) => {
-                    if let Ok(mut unit_orders) = unit_orders_query.get_mut(*entity) {
-                        unit_orders.new_order(UnitOrder::MoveToPosition {
-                            target_position: cursor_position.exact_position_option.unwrap(),
-                        })
+                    if let Ok((unit_id, OfPlayer(player))) = unit_query.get(*entity) {
+                        if *player == viewer_entity {
+                            command_queue.push(
+                                *viewer_id,
+                                GameCommand::MoveUnit {
+                                    unit: *unit_id,
+                                    target: cursor_position.exact_position_option.unwrap(),
+                                },
+                            );
+                        }
+                    }
+                }
+                // Recruit the unit last picked in the city (or the first one it
+                // can) and send it to the cursor
+                SelectedEntity::City(entity) => {
+                    if let Ok((city_id, OfPlayer(player), city_type)) = city_query.get(*entity) {
+                        let recruitable_units = city_type.get_city_stats().recruitable_units;
+                        let unit_type_option = recruit_choice
+                            .0
+                            .get(entity)
+                            .filter(|unit_type| recruitable_units.contains(unit_type))
+                            .or_else(|| recruitable_units.first());
+                        match unit_type_option {
+                            Some(unit_type) if *player == viewer_entity => command_queue.push(
+                                *viewer_id,
+                                GameCommand::RecruitUnit {
+                                    city: *city_id,
+                                    unit_type: *unit_type,
+                                    rally_point: cursor_position.exact_position_option,
+                                },
+                            ),
+                            _ => {}
+                        }
                     }
                 }
-                SelectedEntity::City(_) => todo!(),
             }
         }
     }
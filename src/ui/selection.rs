@@ -143,7 +143,10 @@ impl Selection {
                 let mut new_selected_entities = selected_entities.clone();
                 new_selected_entities.remove(&SelectedEntity::Unit(entity));
                 if new_selected_entities.len() <= 1 {
-                    self.0 = SelectionType::Single(*selected_entities.iter().next().unwrap());
+                    self.0 = match new_selected_entities.iter().next() {
+                        Some(selected_entity) => SelectionType::Single(*selected_entity),
+                        None => SelectionType::None,
+                    };
                 } else {
                     self.0 = SelectionType::Multiple(new_selected_entities);
                 }
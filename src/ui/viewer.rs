@@ -79,6 +79,17 @@ impl ViewerMap {
         self.position_grid.get(position)
     }
 
+    pub fn province_at_position(&self, position: &Position) -> Option<Entity> {
+        self.entities_at_position(position)?
+            .iter()
+            .find_map(|entity| match entity {
+                EntityOnTile::Province {
+                    province_entity, ..
+                } => Some(*province_entity),
+                _ => None,
+            })
+    }
+
     pub fn entities_in_bounding_box(
         &self,
         bounding_box: &(Position, Position),
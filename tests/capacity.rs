@@ -0,0 +1,36 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{loaded_app, RED};
+use lands_of_mana::{
+    game::{map::Position, units::Unit, world::GameId},
+    headless,
+};
+
+#[test]
+fn newest_units_are_disbanded_over_capacity() {
+    // Mage tower gives 5 death capacity, red has 7 skeletons
+    let mut game =
+        common::two_player_game(16, 8, vec![common::province(1, "Test", Some(RED))], |_| 0);
+    common::add_city(&mut game, Some(RED), 0, Position::new(2, 2));
+    for index in 0..7 {
+        common::add_skeleton(&mut game, RED, Position::new(6 + index, 4));
+    }
+    let oldest = game
+        .units
+        .iter()
+        .map(|unit| unit.id)
+        .take(5)
+        .collect::<Vec<_>>();
+    let mut app = loaded_app(game);
+    headless::run_ticks(&mut app, 2);
+
+    let mut remaining = app
+        .world
+        .query_filtered::<&GameId, With<Unit>>()
+        .iter(&app.world)
+        .copied()
+        .collect::<Vec<_>>();
+    remaining.sort_unstable();
+    assert_eq!(remaining, oldest);
+}
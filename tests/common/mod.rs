@@ -0,0 +1,288 @@
+//! Hand-built games and maps shared by integration tests. Every test crate
+//! uses only some of the helpers.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use lands_of_mana::{
+    game::{
+        load_map::{LoadedMap, MapLoadError},
+        map::{Position, TerrainTop, TerrainType},
+        province::{CityType, Province},
+        random::GameRng,
+        recruitment::RecruitmentQueue,
+        save::{
+            LoadGame, SaveGame, SavedCity, SavedPlayer, SavedProvince, SavedTerrain, SavedUnit,
+            SAVE_VERSION,
+        },
+        units::{UnitOrders, UnitType},
+        world::{CapacityResourceType, GameIds, OfPlayer, PlayerName, StockpileResourceType},
+        GameRules,
+    },
+    headless,
+};
+
+pub const RED: usize = 0;
+pub const BLUE: usize = 1;
+
+/// Grass map where Red, the viewer, and Blue have gold, wood and death
+/// capacity but no cities, sites or units. `province_of` says which of
+/// `provinces` a tile is in.
+pub fn two_player_game(
+    width: u32,
+    height: u32,
+    provinces: Vec<SavedProvince>,
+    province_of: impl Fn(Position) -> usize,
+) -> SaveGame {
+    let mut ids = GameIds::default();
+    let players = ["Red", "Blue"]
+        .iter()
+        .zip([Color::RED, Color::BLUE])
+        .enumerate()
+        .map(|(index, (name, color))| SavedPlayer {
+            id: ids.next(),
+            name: name.to_string(),
+            color,
+            viewer: index == RED,
+            stockpiles: vec![
+                (StockpileResourceType::Gold, 1000.),
+                (StockpileResourceType::Wood, 100.),
+            ],
+            capacities: vec![CapacityResourceType::Death],
+        })
+        .collect();
+    let terrain = (0..height)
+        .flat_map(|y| (0..width).map(move |x| Position::new(x, y)))
+        .map(|position| SavedTerrain {
+            province: province_of(position),
+            position,
+            base: TerrainType::GrassLand,
+            top: TerrainTop::None,
+        })
+        .collect();
+
+    SaveGame {
+        version: SAVE_VERSION,
+        game_day: 0,
+        game_tick: 0,
+        first_day: true,
+        rules: GameRules::default(),
+        ids,
+        rng: GameRng::new(42),
+        width,
+        height,
+        players,
+        provinces,
+        terrain,
+        cities: Vec::new(),
+        sites: Vec::new(),
+        units: Vec::new(),
+    }
+}
+
+pub fn province(object_id: u32, name: &str, owner: Option<usize>) -> SavedProvince {
+    SavedProvince {
+        object_id,
+        name: name.to_string(),
+        region: None,
+        owner,
+        occupation: None,
+    }
+}
+
+/// Mage tower with nothing queued
+pub fn add_city(game: &mut SaveGame, player: Option<usize>, province: usize, position: Position) {
+    let id = game.ids.next();
+    game.cities.push(SavedCity {
+        id,
+        player,
+        province,
+        position,
+        city_type: CityType::MageTower,
+        recruitment: RecruitmentQueue::default(),
+    });
+}
+
+/// Skeleton with every figure at full health
+pub fn add_skeleton(game: &mut SaveGame, player: usize, position: Position) {
+    let id = game.ids.next();
+    let stats = UnitType::Skeleton.get_unit_stats();
+    game.units.push(SavedUnit {
+        id,
+        player,
+        unit_type: UnitType::Skeleton,
+        position,
+        figures: (0..stats.max_figures)
+            .map(|figure| (figure, stats.max_health))
+            .collect(),
+        orders: UnitOrders::default(),
+    });
+}
+
+pub fn loaded_app(game: SaveGame) -> App {
+    let mut app = headless::build_app(None);
+    app.insert_resource(LoadGame(game));
+    headless::run_until_in_game(&mut app).unwrap();
+    app
+}
+
+pub fn player_entity(app: &mut App, name: &str) -> Entity {
+    app.world
+        .query::<(Entity, &PlayerName)>()
+        .iter(&app.world)
+        .find(|(_, PlayerName(player_name))| player_name == name)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+/// Province entity by province name
+pub fn province_entities(app: &mut App) -> HashMap<String, Entity> {
+    app.world
+        .query::<(Entity, &Province)>()
+        .iter(&app.world)
+        .map(|(entity, province)| (province.name.clone(), entity))
+        .collect()
+}
+
+/// Owner name of every province by province name
+pub fn province_owners(app: &mut App) -> HashMap<String, Option<String>> {
+    let player_names = app
+        .world
+        .query::<(Entity, &PlayerName)>()
+        .iter(&app.world)
+        .map(|(entity, PlayerName(name))| (entity, name.clone()))
+        .collect::<HashMap<_, _>>();
+    app.world
+        .query::<(&Province, Option<&OfPlayer>)>()
+        .iter(&app.world)
+        .map(|(province, owner_option)| {
+            (
+                province.name.clone(),
+                owner_option.map(|OfPlayer(owner)| player_names[owner].clone()),
+            )
+        })
+        .collect()
+}
+
+/// Small map written as TMX, for tests of map loading. Base tiles are grass
+/// unless changed, other tile layers are empty.
+pub struct TestMap {
+    pub width: u32,
+    pub height: u32,
+    // Tiled gids (tile id + 1) of the base layer, rows going down
+    pub base: Vec<u32>,
+    // `<object>` elements of each object layer
+    pub provinces: Vec<String>,
+    pub cities: Vec<String>,
+    pub sites: Vec<String>,
+}
+
+pub const GRASS_GID: u32 = TerrainType::GrassLand as u32 + 1;
+pub const WATER_GID: u32 = TerrainType::Water as u32 + 1;
+
+impl TestMap {
+    pub fn new(width: u32, height: u32) -> TestMap {
+        TestMap {
+            width,
+            height,
+            base: vec![GRASS_GID; (width * height) as usize],
+            provinces: Vec::new(),
+            cities: Vec::new(),
+            sites: Vec::new(),
+        }
+    }
+
+    /// Set base tile at Tiled coordinates
+    pub fn set_base(&mut self, x: u32, y: u32, gid: u32) {
+        self.base[(y * self.width + x) as usize] = gid;
+    }
+
+    /// Rectangle province in tiles, at Tiled coordinates
+    pub fn add_province(
+        &mut self,
+        id: u32,
+        name: &str,
+        (x, y, width, height): (u32, u32, u32, u32),
+    ) {
+        self.provinces.push(format!(
+            r#"<object id="{}" name="{}" x="{}" y="{}"><polygon points="0,0 {w},0 {w},{h} 0,{h}"/></object>"#,
+            id,
+            name,
+            x * 16,
+            y * 16,
+            w = width * 16,
+            h = height * 16,
+        ));
+    }
+
+    /// City at pixel coordinates, with given `<property>` elements
+    pub fn add_city(&mut self, id: u32, name: &str, (x, y): (f32, f32), properties: &str) {
+        self.cities.push(format!(
+            r#"<object id="{}" name="{}" x="{}" y="{}"><properties>{}</properties></object>"#,
+            id, name, x, y, properties
+        ));
+    }
+
+    /// Site at pixel coordinates, with given `<property>` elements
+    pub fn add_site(&mut self, id: u32, name: &str, (x, y): (f32, f32), properties: &str) {
+        self.sites.push(format!(
+            r#"<object id="{}" name="{}" x="{}" y="{}"><properties>{}</properties></object>"#,
+            id, name, x, y, properties
+        ));
+    }
+
+    pub fn to_tmx(&self) -> String {
+        let empty = vec!["0"; (self.width * self.height) as usize].join(",");
+        let base = self
+            .base
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let tile_layer = |id: u32, name: &str, data: &str| {
+            format!(
+                r#"<layer id="{}" name="{}" width="{}" height="{}"><data encoding="csv">{}</data></layer>"#,
+                id, name, self.width, self.height, data
+            )
+        };
+        let object_layer = |id: u32, name: &str, objects: &[String]| {
+            format!(
+                r#"<objectgroup id="{}" name="{}">{}</objectgroup>"#,
+                id,
+                name,
+                objects.join("")
+            )
+        };
+        [
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+            format!(
+                r#"<map version="1.8" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="16" tileheight="16" infinite="0">"#,
+                self.width, self.height
+            ),
+            r#"<tileset firstgid="1" name="base" tilewidth="16" tileheight="16" tilecount="32" columns="8"><image source="terrain_base.png" width="128" height="64"/></tileset>"#.to_string(),
+            tile_layer(1, "Base", &base),
+            tile_layer(2, "Rivers", &empty),
+            tile_layer(3, "Roads", &empty),
+            tile_layer(4, "ForestsAndMountains", &empty),
+            object_layer(5, "Provinces", &self.provinces),
+            object_layer(6, "Cities", &self.cities),
+            object_layer(7, "Sites", &self.sites),
+            "</map>".to_string(),
+        ]
+        .join("\n")
+    }
+
+    /// Write the map to a temporary file and read it
+    pub fn read(&self, name: &str) -> Result<LoadedMap, MapLoadError> {
+        let path = std::env::temp_dir().join(format!(
+            "lands_of_mana_map_{}_{}.tmx",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, self.to_tmx()).unwrap();
+        let result = LoadedMap::read(&path);
+        std::fs::remove_file(&path).ok();
+        result
+    }
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use clap::Parser;
+use common::{loaded_app, RED};
+use lands_of_mana::{
+    game::{
+        checksum::StateChecksum,
+        map::Position,
+        player_commands::{GameCommand, PlayerCommandQueue},
+        random::GameRng,
+        recruitment::{QueuedRecruit, RecruitmentQueue},
+        replay::{RecordReplay, Replay},
+        save::{LoadGame, SaveGame},
+        start::{self, StartError},
+        units::UnitType,
+    },
+    headless,
+    options::GameOptions,
+};
+
+const TICKS: usize = 60;
+
+/// Small game where two players fight next to each other and one of them
+/// recruits, so that movement, combat, recruitment and upkeep all happen
+fn test_game(skeletons: usize) -> SaveGame {
+    let mut game =
+        common::two_player_game(16, 16, vec![common::province(1, "Test", Some(RED))], |_| 0);
+    common::add_city(&mut game, Some(RED), 0, Position::new(2, 2));
+    game.cities[0].recruitment = RecruitmentQueue {
+        queue: VecDeque::from([QueuedRecruit {
+            unit_type: UnitType::Skeleton,
+            ticks_left: 5,
+            rally_point: Some(Position::new(8, 8)),
+        }]),
+    };
+    for index in 0..skeletons {
+        common::add_skeleton(
+            &mut game,
+            index % 2,
+            Position::new(7 + (index % 2) as u32 * 2, 7 + index as u32 / 2),
+        );
+    }
+    game
+}
+
+fn run_checksums(app: &mut App) -> Vec<StateChecksum> {
+    run_checksums_with(app, |_, _| {})
+}
+
+fn run_checksums_with(
+    app: &mut App,
+    mut before_tick: impl FnMut(&mut App, usize),
+) -> Vec<StateChecksum> {
+    (0..TICKS)
+        .map(|tick| {
+            before_tick(app, tick);
+            headless::run_ticks(app, 1);
+            *app.world.resource::<StateChecksum>()
+        })
+        .collect()
+}
+
+fn fixture_map() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/small.tmx")
+}
+
+/// App that starts a new game on the fixture map with given command line
+/// arguments. Options are inserted after plugins are built, so only systems
+/// read them (eg `load_map` reading `--seed`) and the game isn't recorded.
+fn map_app(arguments: &[&str]) -> App {
+    let options = GameOptions::parse_from(["lands_of_mana"].iter().chain(arguments));
+    let mut app = headless::build_app(Some(fixture_map()));
+    app.insert_resource(options);
+    headless::run_until_in_game(&mut app).unwrap();
+    app
+}
+
+#[test]
+fn same_map_and_setup_seed_have_same_checksums() {
+    let mut first_app = map_app(&[]);
+    assert_eq!(first_app.world.resource::<GameRng>().seed(), 42);
+    let first = run_checksums(&mut first_app);
+    let second = run_checksums(&mut map_app(&[]));
+    assert_eq!(first, second);
+}
+
+#[test]
+fn seed_option_is_rejected_for_saved_game() {
+    let options = GameOptions::parse_from(["lands_of_mana", "saves/game.ron", "--seed", "7"]);
+    let result = start::insert_start_resources(&mut App::new(), &options);
+    assert!(matches!(
+        result,
+        Err(StartError::OnlyForNewGames {
+            option: "--seed",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn setup_option_is_rejected_for_replay() {
+    let options = GameOptions::parse_from([
+        "lands_of_mana",
+        "saves/game.replay.ron",
+        "--setup",
+        "setup.ron",
+    ]);
+    let result = start::insert_start_resources(&mut App::new(), &options);
+    assert!(matches!(
+        result,
+        Err(StartError::OnlyForNewGames {
+            option: "--setup",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn seed_option_overrides_setup_seed() {
+    let mut first_app = map_app(&["--seed", "7"]);
+    assert_eq!(first_app.world.resource::<GameRng>().seed(), 7);
+    let first = run_checksums(&mut first_app);
+    let second = run_checksums(&mut map_app(&["--seed", "7"]));
+    assert_eq!(first, second);
+    let setup_seed = run_checksums(&mut map_app(&[]));
+    assert_ne!(first, setup_seed);
+}
+
+#[test]
+fn same_game_has_same_checksums() {
+    let first = run_checksums(&mut loaded_app(test_game(4)));
+    let second = run_checksums(&mut loaded_app(test_game(4)));
+    assert_eq!(first, second);
+}
+
+#[test]
+fn different_games_have_different_checksums() {
+    let first = run_checksums(&mut loaded_app(test_game(4)));
+    let second = run_checksums(&mut loaded_app(test_game(2)));
+    assert_ne!(first.last(), second.last());
+}
+
+#[test]
+fn loaded_save_continues_like_saved_game() {
+    let path = std::env::temp_dir().join(format!(
+        "lands_of_mana_determinism_{}.ron",
+        std::process::id()
+    ));
+    let mut app = loaded_app(test_game(4));
+    headless::run_ticks(&mut app, TICKS / 2);
+    SaveGame::from_world(&mut app.world)
+        .and_then(|save| save.write(&path))
+        .expect("game should be saved");
+    let save = SaveGame::read(&path);
+    std::fs::remove_file(&path).ok();
+    let mut loaded = loaded_app(save.expect("save should be readable"));
+
+    assert_eq!(
+        StateChecksum::from_world(&mut app.world),
+        StateChecksum::from_world(&mut loaded.world)
+    );
+    assert_eq!(run_checksums(&mut app), run_checksums(&mut loaded));
+}
+
+#[test]
+fn replay_has_same_checksums_as_recorded_game() {
+    let path = std::env::temp_dir().join(format!(
+        "lands_of_mana_determinism_{}.replay.ron",
+        std::process::id()
+    ));
+    let game = test_game(4);
+    // First unit belongs to the first player
+    let player = game.players[game.units[0].player].id;
+    let unit = game.units[0].id;
+    let mut app = headless::build_app(None);
+    app.insert_resource(LoadGame(game))
+        .insert_resource(RecordReplay(path.clone()));
+    headless::run_until_in_game(&mut app).unwrap();
+    let recorded = run_checksums_with(&mut app, |app, tick| {
+        if tick == 3 {
+            app.world.resource_mut::<PlayerCommandQueue>().push(
+                player,
+                GameCommand::MoveUnit {
+                    unit,
+                    target: Position::new(3, 12),
+                },
+            );
+        }
+    });
+
+    let mut replay_app = headless::build_app(None);
+    Replay::read(&path)
+        .expect("replay should have been recorded")
+        .insert_into(&mut replay_app);
+    headless::run_until_in_game(&mut replay_app).unwrap();
+    let replayed = run_checksums(&mut replay_app);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(recorded, replayed);
+    // Command should have changed how the game went
+    let unrecorded = run_checksums(&mut loaded_app(test_game(4)));
+    assert_ne!(recorded.last(), unrecorded.last());
+}
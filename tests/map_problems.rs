@@ -0,0 +1,132 @@
+mod common;
+
+use common::{TestMap, WATER_GID};
+use lands_of_mana::game::{
+    load_map::{MapLoadError, MapProblem},
+    map_check::check_map,
+};
+
+const MAGE_TOWER: &str = r#"<property name="city_type" value="MageTower"/>"#;
+
+fn problems(map: &TestMap, name: &str) -> Vec<MapProblem> {
+    match map.read(name) {
+        Err(MapLoadError::Invalid { problems, .. }) => problems,
+        Err(error) => panic!("{}", error),
+        Ok(_) => Vec::new(),
+    }
+}
+
+/// Problems found by the map checker on a map that loads
+fn check(map: &TestMap, name: &str) -> Vec<MapProblem> {
+    check_map(&map.read(name).unwrap())
+}
+
+#[test]
+fn objects_below_the_map_are_outside_it() {
+    // Province reaches past the bottom edge of the map
+    let mut map = TestMap::new(4, 4);
+    map.add_province(1, "Tall", (0, 0, 4, 6));
+    map.add_city(2, "Sunken Tower", (16., 72.), MAGE_TOWER);
+    map.add_site(
+        3,
+        "Sunken Mine",
+        (40., 80.),
+        r#"<property name="site_type" value="GoldMine"/>"#,
+    );
+
+    let problems = problems(&map, "outside");
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::CityOutsideMap { object_id: 2, .. })));
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::SiteOutsideMap { object_id: 3, .. })));
+}
+
+#[test]
+fn all_problems_are_reported_at_once() {
+    let mut map = TestMap::new(4, 4);
+    // Last column isn't in any province
+    map.add_province(1, "West", (0, 0, 3, 4));
+    // Tile id 23 isn't a terrain type
+    map.set_base(0, 0, 24);
+    map.add_city(2, "Nameless", (16., 16.), "");
+    map.add_city(
+        3,
+        "Odd Tower",
+        (32., 32.),
+        r#"<property name="city_type" value="OddTower"/>"#,
+    );
+
+    let problems = problems(&map, "several");
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::UnknownTile { x: 0, y: 0, .. })));
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::TileOutsideProvinces { x: 3, .. })));
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::MissingCityType { object_id: 2, .. })));
+    assert!(problems
+        .iter()
+        .any(|problem| matches!(problem, MapProblem::UnknownCityType { object_id: 3, .. })));
+}
+
+#[test]
+fn overlapping_provinces_are_reported() {
+    let mut map = TestMap::new(4, 4);
+    map.add_province(1, "West", (0, 0, 3, 4));
+    map.add_province(2, "East", (2, 0, 2, 4));
+
+    let overlaps = check(&map, "overlap")
+        .into_iter()
+        .filter_map(|problem| match problem {
+            MapProblem::OverlappingProvinces {
+                x, mut object_ids, ..
+            } => {
+                object_ids.sort_unstable();
+                Some((x, object_ids))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(overlaps, vec![(2, vec![1, 2]); 4]);
+}
+
+#[test]
+fn unknown_players_are_reported() {
+    let mut map = TestMap::new(4, 4);
+    map.add_province(1, "All", (0, 0, 4, 4));
+    map.add_city(
+        2,
+        "Tower",
+        (16., 16.),
+        &format!(r#"{}<property name="player" value="Nobody"/>"#, MAGE_TOWER),
+    );
+
+    let problems = check(&map, "player");
+    assert!(problems.iter().any(|problem| matches!(
+        problem,
+        MapProblem::UnknownPlayer { object_id: 2, value, .. } if value == "Nobody"
+    )));
+}
+
+#[test]
+fn unreachable_land_is_reported() {
+    // Water column cuts off the two western columns
+    let mut map = TestMap::new(8, 4);
+    for y in 0..4 {
+        map.set_base(2, y, WATER_GID);
+    }
+    map.add_province(1, "All", (0, 0, 8, 4));
+
+    let unreachable = check(&map, "unreachable")
+        .into_iter()
+        .filter_map(|problem| match problem {
+            MapProblem::UnreachableLand { tiles, x, .. } => Some((tiles, x)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(unreachable, vec![(8, 0)]);
+}
@@ -0,0 +1,43 @@
+use lands_of_mana::{
+    game::{
+        load_map::forest_and_mountains_subtype,
+        map::{ForestType, MountainType, TerrainTop},
+    },
+    render::tilemap::{get_base_forest_texture_id, get_base_mountain_texture_id},
+};
+use strum::IntoEnumIterator;
+
+fn subtype_name(id: u32) -> Option<String> {
+    forest_and_mountains_subtype(id).map(|top| format!("{:?}", top))
+}
+
+#[test]
+fn forest_blocks_map_back_to_their_type() {
+    for forest_type in ForestType::iter() {
+        assert_eq!(
+            subtype_name(get_base_forest_texture_id(forest_type)),
+            Some(format!("{:?}", TerrainTop::Forest(forest_type)))
+        );
+    }
+}
+
+#[test]
+fn mountain_blocks_map_back_to_their_type() {
+    for mountain_type in MountainType::iter() {
+        let expected = Some(format!("{:?}", TerrainTop::Mountain(mountain_type)));
+        let id = get_base_mountain_texture_id(mountain_type);
+        assert_eq!(subtype_name(id), expected);
+        // Last column of the block, and the same block a row lower
+        assert_eq!(subtype_name(id + 12), expected);
+        assert_eq!(subtype_name(id + 52), expected);
+    }
+}
+
+#[test]
+fn tiles_before_forests_are_rejected() {
+    assert_eq!(subtype_name(0), None);
+    assert_eq!(
+        subtype_name(get_base_forest_texture_id(ForestType::Beech) - 1),
+        None
+    );
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{loaded_app, RED};
+use lands_of_mana::{
+    game::{
+        map::{MapTerrain, Position, TerrainTop, TerrainType},
+        movement::{MovementRules, MovementType},
+        navigation,
+        units::{Unit, UnitOrder},
+    },
+    headless,
+};
+
+/// Grass terrain with given tiles changed
+fn terrain(width: u32, height: u32, tiles: &[(Position, TerrainType, TerrainTop)]) -> MapTerrain {
+    let mut terrain = MapTerrain::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            terrain.set(
+                &Position::new(x, y),
+                TerrainType::GrassLand,
+                TerrainTop::None,
+            );
+        }
+    }
+    for (position, base, top) in tiles {
+        terrain.set(position, *base, *top);
+    }
+    terrain
+}
+
+fn foot() -> MovementRules {
+    MovementType::Foot.get_movement_rules()
+}
+
+fn water(x: u32, y: u32) -> (Position, TerrainType, TerrainTop) {
+    (Position::new(x, y), TerrainType::Water, TerrainTop::None)
+}
+
+fn cliff(x: u32, y: u32) -> (Position, TerrainType, TerrainTop) {
+    (
+        Position::new(x, y),
+        TerrainType::GrassLand,
+        TerrainTop::Cliff,
+    )
+}
+
+#[test]
+fn path_goes_around_water_and_cliffs() {
+    // Wall across the map with a gap at the top
+    let terrain = terrain(7, 5, &[water(3, 0), water(3, 1), water(3, 2), cliff(3, 3)]);
+    let rules = foot();
+    let from = Position::new(0, 0);
+    let to = Position::new(6, 0);
+
+    let path = navigation::find_path(&terrain, &rules, &from, &to).unwrap();
+    assert_eq!(path.last(), Some(&to));
+    assert!(path.contains(&Position::new(3, 4)));
+    let mut position = from;
+    for next_position in path.iter() {
+        assert!(navigation::can_step(
+            &terrain,
+            &rules,
+            &position,
+            next_position
+        ));
+        position = *next_position;
+    }
+}
+
+#[test]
+fn unreachable_target_has_no_path() {
+    let terrain = terrain(
+        7,
+        5,
+        &[
+            water(3, 0),
+            water(3, 1),
+            water(3, 2),
+            cliff(3, 3),
+            water(3, 4),
+        ],
+    );
+    let rules = foot();
+    assert_eq!(
+        navigation::find_path(&terrain, &rules, &Position::new(0, 0), &Position::new(6, 0)),
+        None
+    );
+    // Target itself can't be entered
+    assert_eq!(
+        navigation::find_path(&terrain, &rules, &Position::new(0, 0), &Position::new(3, 0)),
+        None
+    );
+}
+
+#[test]
+fn diagonal_doesnt_squeeze_between_blocked_tiles() {
+    let rules = foot();
+    let from = Position::new(0, 0);
+    let to = Position::new(1, 1);
+
+    let blocked = terrain(3, 3, &[water(1, 0), water(0, 1)]);
+    assert!(!navigation::can_step(&blocked, &rules, &from, &to));
+    assert_eq!(navigation::find_path(&blocked, &rules, &from, &to), None);
+
+    let one_side_open = terrain(3, 3, &[water(1, 0)]);
+    assert!(navigation::can_step(&one_side_open, &rules, &from, &to));
+    assert_eq!(
+        navigation::find_path(&one_side_open, &rules, &from, &to),
+        Some(vec![to])
+    );
+}
+
+#[test]
+fn blocked_next_step_makes_unit_replan() {
+    let target = Position::new(10, 4);
+    let mut game = common::two_player_game(16, 8, vec![common::province(1, "Test", None)], |_| 0);
+    // City gives the death capacity skeleton needs
+    common::add_city(&mut game, Some(RED), 0, Position::new(14, 6));
+    common::add_skeleton(&mut game, RED, Position::new(2, 4));
+    game.units[0]
+        .orders
+        .new_order(UnitOrder::move_to_position(target));
+    let mut app = loaded_app(game);
+
+    // Path is planned on the first tick, then a wall is built across it
+    // with a gap at the bottom
+    headless::run_ticks(&mut app, 1);
+    let mut terrain = app
+        .world
+        .query::<&mut MapTerrain>()
+        .single_mut(&mut app.world);
+    for y in 1..8 {
+        terrain.set(&Position::new(6, y), TerrainType::Water, TerrainTop::None);
+    }
+
+    let mut positions = Vec::new();
+    for _ in 0..200 {
+        headless::run_ticks(&mut app, 1);
+        let position = *app
+            .world
+            .query_filtered::<&Position, With<Unit>>()
+            .single(&app.world);
+        positions.push(position);
+        if position == target {
+            break;
+        }
+    }
+    assert_eq!(positions.last(), Some(&target));
+    assert!(positions.contains(&Position::new(6, 0)));
+}
@@ -0,0 +1,23 @@
+use clap::Parser;
+use lands_of_mana::options::GameOptions;
+
+fn parse(arguments: &[&str]) -> Result<GameOptions, clap::Error> {
+    GameOptions::try_parse_from(["lands_of_mana"].iter().chain(arguments))
+}
+
+#[test]
+fn window_size_is_parsed() {
+    let options = parse(&["--windowed", "1600x1000"]).unwrap();
+    assert_eq!(options.windowed, Some((1600., 1000.)));
+}
+
+#[test]
+fn window_size_has_to_be_positive() {
+    for size in ["0x0", "1600x0", "-1600x1000", "1600x-1000", "infx1000"] {
+        assert!(
+            parse(&[&format!("--windowed={}", size)]).is_err(),
+            "{} should be rejected",
+            size
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use euclid::point2;
+use lands_of_mana::game::polygon::{Polygon, PolygonError};
+
+fn l_shape() -> Polygon {
+    Polygon::new(vec![
+        point2(0., 0.),
+        point2(32., 0.),
+        point2(32., 16.),
+        point2(16., 16.),
+        point2(16., 32.),
+        point2(0., 32.),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn concave_polygon_doesnt_contain_its_notch() {
+    let polygon = l_shape();
+    assert!(polygon.contains_point(point2(8., 8.)));
+    assert!(polygon.contains_point(point2(24., 8.)));
+    assert!(polygon.contains_point(point2(8., 24.)));
+    assert!(!polygon.contains_point(point2(24., 24.)));
+    assert!(!polygon.contains_point(point2(40., 8.)));
+}
+
+#[test]
+fn hole_is_not_in_polygon() {
+    let mut polygon = Polygon::new(vec![
+        point2(0., 0.),
+        point2(48., 0.),
+        point2(48., 48.),
+        point2(0., 48.),
+    ])
+    .unwrap();
+    polygon
+        .add_hole(vec![
+            point2(16., 16.),
+            point2(32., 16.),
+            point2(32., 32.),
+            point2(16., 32.),
+        ])
+        .unwrap();
+    assert!(polygon.contains_point(point2(8., 8.)));
+    assert!(!polygon.contains_point(point2(24., 24.)));
+    assert!(polygon.contains_point(point2(40., 40.)));
+}
+
+#[test]
+fn broken_polygons_are_rejected() {
+    let bow_tie = Polygon::new(vec![
+        point2(0., 0.),
+        point2(32., 32.),
+        point2(32., 0.),
+        point2(0., 32.),
+    ]);
+    assert_eq!(bow_tie.unwrap_err(), PolygonError::SelfIntersecting);
+    let line = Polygon::new(vec![point2(0., 0.), point2(16., 16.), point2(32., 32.)]);
+    assert_eq!(line.unwrap_err(), PolygonError::NoArea);
+}
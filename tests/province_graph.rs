@@ -0,0 +1,75 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{loaded_app, province_entities};
+use lands_of_mana::game::{
+    map::{Position, TerrainTop},
+    province_graph::ProvinceGraph,
+    GameWorld,
+};
+
+#[test]
+fn neighbouring_provinces_are_connected_in_graph() {
+    let mut game = common::two_player_game(
+        16,
+        8,
+        vec![
+            common::province(1, "West", None),
+            common::province(2, "East", None),
+        ],
+        |position| (position.x >= 8) as usize,
+    );
+    for terrain in game.terrain.iter_mut() {
+        if terrain.position == Position::new(7, 3) {
+            terrain.top = TerrainTop::River;
+        }
+    }
+    let mut app = loaded_app(game);
+
+    let provinces = province_entities(&mut app);
+    let graph = app
+        .world
+        .query_filtered::<&ProvinceGraph, With<GameWorld>>()
+        .single(&app.world);
+    let connection = graph
+        .connection(provinces["West"], provinces["East"])
+        .unwrap();
+    assert_eq!(connection.border_length, 8);
+    assert!(connection.river);
+    assert!(!connection.road);
+    assert!(!connection.sea);
+    assert_eq!(
+        graph.neighbors(provinces["East"]).collect::<Vec<_>>(),
+        vec![(provinces["West"], connection)]
+    );
+}
+
+#[test]
+fn neighbors_are_ordered_by_object_id() {
+    // Spawned west to east, but object ids go the other way
+    let game = common::two_player_game(
+        12,
+        4,
+        vec![
+            common::province(5, "West", None),
+            common::province(3, "Middle", None),
+            common::province(1, "East", None),
+        ],
+        |position| (position.x / 4) as usize,
+    );
+    let mut app = loaded_app(game);
+
+    let provinces = province_entities(&mut app);
+    let graph = app
+        .world
+        .query_filtered::<&ProvinceGraph, With<GameWorld>>()
+        .single(&app.world);
+    assert_eq!(
+        graph
+            .neighbors(provinces["Middle"])
+            .map(|(province, _)| province)
+            .collect::<Vec<_>>(),
+        vec![provinces["East"], provinces["West"]]
+    );
+    assert!(!graph.are_neighbors(provinces["West"], provinces["East"]));
+}
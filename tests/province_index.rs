@@ -0,0 +1,105 @@
+use euclid::{default::Point2D, point2};
+use lands_of_mana::game::{load_map::MapProvince, polygon::Polygon, province_index::ProvinceIndex};
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 6;
+
+fn province(object_id: u32, polygon: Polygon) -> MapProvince {
+    MapProvince {
+        object_id,
+        name: format!("Province {}", object_id),
+        region: None,
+        player: None,
+        polygon,
+    }
+}
+
+fn points(points: &[(f32, f32)]) -> Vec<Point2D<f32>> {
+    points.iter().map(|(x, y)| point2(*x, *y)).collect()
+}
+
+/// Concave, holed, unaligned and past the map edge provinces, some of them
+/// overlapping
+fn provinces() -> Vec<MapProvince> {
+    let l_shape = Polygon::new(points(&[
+        (0., 0.),
+        (48., 0.),
+        (48., 16.),
+        (16., 16.),
+        (16., 48.),
+        (0., 48.),
+    ]))
+    .unwrap();
+    let mut holed =
+        Polygon::new(points(&[(48., 0.), (128., 0.), (128., 64.), (48., 64.)])).unwrap();
+    holed
+        .add_hole(points(&[(64., 16.), (96., 16.), (96., 48.), (64., 48.)]))
+        .unwrap();
+    let unaligned = Polygon::new(points(&[(20.5, 30.25), (70.75, 60.), (10., 90.5)])).unwrap();
+    let past_edge = Polygon::new(points(&[
+        (100., 70.),
+        (160., 70.),
+        (160., 120.),
+        (100., 120.),
+    ]))
+    .unwrap();
+    vec![
+        province(1, l_shape),
+        province(2, holed),
+        province(3, unaligned),
+        province(4, past_edge),
+    ]
+}
+
+fn linear_scan(provinces: &[MapProvince], point: Point2D<f32>) -> Vec<usize> {
+    (0..provinces.len())
+        .filter(|index| provinces[*index].polygon.contains_point(point))
+        .collect()
+}
+
+fn assert_same_as_linear_scan(
+    provinces: &[MapProvince],
+    points: impl Iterator<Item = Point2D<f32>>,
+) {
+    let index = ProvinceIndex::new(provinces, WIDTH, HEIGHT);
+    for point in points {
+        assert_eq!(
+            index.provinces_at(provinces, point).collect::<Vec<_>>(),
+            linear_scan(provinces, point),
+            "provinces at {:?}",
+            point
+        );
+    }
+}
+
+#[test]
+fn index_finds_same_provinces_as_linear_scan() {
+    let provinces = provinces();
+    // Every 2 pixels, so tile edges are included
+    let points = (0..HEIGHT * 8)
+        .flat_map(|y| (0..WIDTH * 8).map(move |x| point2(x as f32 * 2., y as f32 * 2.)));
+    assert_same_as_linear_scan(&provinces, points);
+}
+
+#[test]
+fn index_finds_same_provinces_on_bounding_box_edges() {
+    let provinces = provinces();
+    let map_width = (WIDTH * 16) as f32;
+    let map_height = (HEIGHT * 16) as f32;
+    let points = provinces
+        .iter()
+        .flat_map(|province| {
+            let bounding_box = province.polygon.bounding_box();
+            let xs = [bounding_box.min.x, bounding_box.max.x];
+            let ys = [bounding_box.min.y, bounding_box.max.y];
+            let middle = bounding_box.center();
+            xs.into_iter()
+                .flat_map(move |x| [point2(x, middle.y), point2(x, ys[0]), point2(x, ys[1])])
+                .chain(ys.into_iter().map(move |y| point2(middle.x, y)))
+                .collect::<Vec<_>>()
+        })
+        .filter(|point| point.x < map_width && point.y < map_height)
+        .collect::<Vec<_>>();
+    assert!(!points.is_empty());
+    assert_same_as_linear_scan(&provinces, points.into_iter());
+}
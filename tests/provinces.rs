@@ -0,0 +1,162 @@
+mod common;
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+use common::{loaded_app, player_entity, province_entities, province_owners, BLUE, RED};
+use lands_of_mana::{
+    game::{
+        map::Position,
+        province::{City, ProvinceOwnerChanged},
+        recruitment::{QueuedRecruit, RecruitmentQueue},
+        save::{SaveGame, SavedSite},
+        site::{Site, SiteType},
+        units::{Unit, UnitType},
+        world::OfPlayer,
+    },
+    headless,
+};
+
+const OCCUPATION_TICKS: u32 = 5;
+
+/// Red owns the west province with its city, east province is neutral with
+/// a gold mine
+fn two_province_game() -> SaveGame {
+    let mut game = common::two_player_game(
+        16,
+        8,
+        vec![
+            common::province(1, "West", Some(RED)),
+            common::province(2, "East", None),
+        ],
+        |position| (position.x >= 8) as usize,
+    );
+    game.rules.province_occupation_ticks = OCCUPATION_TICKS;
+    common::add_city(&mut game, Some(RED), 0, Position::new(2, 2));
+    let id = game.ids.next();
+    game.sites.push(SavedSite {
+        id,
+        player: None,
+        province: 1,
+        position: Position::new(12, 4),
+        site_type: SiteType::GoldMine,
+    });
+    game
+}
+
+/// Two provinces with a red skeleton standing in the east one
+fn occupation_game() -> SaveGame {
+    let mut game = two_province_game();
+    common::add_skeleton(&mut game, RED, Position::new(10, 4));
+    game
+}
+
+/// Run ticks and collect every owner change sent meanwhile
+fn run_ticks_with_owner_changes(app: &mut App, ticks: usize) -> Vec<ProvinceOwnerChanged> {
+    let mut reader = ManualEventReader::<ProvinceOwnerChanged>::default();
+    let mut changes = Vec::new();
+    for _ in 0..ticks {
+        headless::run_ticks(app, 1);
+        let events = app.world.resource::<Events<ProvinceOwnerChanged>>();
+        changes.extend(reader.iter(events).copied());
+    }
+    changes
+}
+
+#[test]
+fn units_take_province_after_occupying_it() {
+    let mut app = loaded_app(occupation_game());
+
+    headless::run_ticks(&mut app, OCCUPATION_TICKS as usize - 1);
+    let owners = province_owners(&mut app);
+    assert_eq!(owners["West"].as_deref(), Some("Red"));
+    assert_eq!(owners["East"], None);
+
+    headless::run_ticks(&mut app, 1);
+    let owners = province_owners(&mut app);
+    assert_eq!(owners["West"].as_deref(), Some("Red"));
+    assert_eq!(owners["East"].as_deref(), Some("Red"));
+}
+
+#[test]
+fn occupation_sends_owner_changed() {
+    let mut app = loaded_app(occupation_game());
+    let changes = run_ticks_with_owner_changes(&mut app, OCCUPATION_TICKS as usize + 1);
+
+    let provinces = province_entities(&mut app);
+    let red = player_entity(&mut app, "Red");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].province, provinces["East"]);
+    assert_eq!(changes[0].previous, None);
+    assert_eq!(changes[0].owner, red);
+}
+
+#[test]
+fn occupier_takes_sites_of_province() {
+    let mut app = loaded_app(occupation_game());
+    headless::run_ticks(&mut app, OCCUPATION_TICKS as usize + 1);
+
+    let red = player_entity(&mut app, "Red");
+    let site_owners = app
+        .world
+        .query_filtered::<Option<&OfPlayer>, With<Site>>()
+        .iter(&app.world)
+        .map(|owner_option| owner_option.map(|OfPlayer(owner)| *owner))
+        .collect::<Vec<_>>();
+    assert_eq!(site_owners, vec![Some(red)]);
+}
+
+#[test]
+fn province_follows_owner_of_its_city() {
+    let mut app = loaded_app(two_province_game());
+    let blue = player_entity(&mut app, "Blue");
+    let red = player_entity(&mut app, "Red");
+    let city = app
+        .world
+        .query_filtered::<Entity, With<City>>()
+        .single(&app.world);
+    app.world.entity_mut(city).insert(OfPlayer(blue));
+
+    let changes = run_ticks_with_owner_changes(&mut app, 1);
+    let owners = province_owners(&mut app);
+    assert_eq!(owners["West"].as_deref(), Some("Blue"));
+    assert_eq!(owners["East"], None);
+    let provinces = province_entities(&mut app);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].province, provinces["West"]);
+    assert_eq!(changes[0].previous, Some(red));
+    assert_eq!(changes[0].owner, blue);
+}
+
+#[test]
+fn occupier_doesnt_get_recruits_of_previous_owner() {
+    // East belongs to Blue, whose city there is recruiting a skeleton
+    let mut game = occupation_game();
+    game.provinces[1].owner = Some(BLUE);
+    common::add_city(&mut game, Some(BLUE), 1, Position::new(12, 1));
+    let blue_city = game.cities.len() - 1;
+    game.cities[blue_city].recruitment = RecruitmentQueue {
+        queue: VecDeque::from([QueuedRecruit {
+            unit_type: UnitType::Skeleton,
+            ticks_left: OCCUPATION_TICKS + 2,
+            rally_point: None,
+        }]),
+    };
+    let mut app = loaded_app(game);
+    headless::run_ticks(&mut app, OCCUPATION_TICKS as usize + 4);
+
+    let red = player_entity(&mut app, "Red");
+    assert_eq!(province_owners(&mut app)["East"].as_deref(), Some("Red"));
+    let mut city_query = app
+        .world
+        .query_filtered::<(&OfPlayer, &RecruitmentQueue), With<City>>();
+    assert!(city_query
+        .iter(&app.world)
+        .all(|(OfPlayer(owner), recruitment)| *owner == red && recruitment.queue.is_empty()));
+    let unit_count = app
+        .world
+        .query_filtered::<(), With<Unit>>()
+        .iter(&app.world)
+        .count();
+    assert_eq!(unit_count, 1);
+}
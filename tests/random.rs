@@ -0,0 +1,40 @@
+use lands_of_mana::game::random::{GameRng, RngStream};
+use rand::Rng;
+
+fn rolls(game_rng: &mut GameRng, stream: RngStream) -> Vec<u32> {
+    (0..10)
+        .map(|_| game_rng.stream(stream).gen_range(0..1000))
+        .collect()
+}
+
+#[test]
+fn same_seed_rolls_same_numbers() {
+    let mut first = GameRng::new(7);
+    let mut second = GameRng::new(7);
+    assert_eq!(
+        rolls(&mut first, RngStream::Combat),
+        rolls(&mut second, RngStream::Combat)
+    );
+}
+
+#[test]
+fn streams_are_independent() {
+    let mut first = GameRng::new(7);
+    let mut second = GameRng::new(7);
+    rolls(&mut second, RngStream::Events);
+    assert_eq!(
+        rolls(&mut first, RngStream::Combat),
+        rolls(&mut second, RngStream::Combat)
+    );
+}
+
+#[test]
+fn saved_rng_continues_where_it_was() {
+    let mut game_rng = GameRng::new(7);
+    rolls(&mut game_rng, RngStream::Combat);
+    let mut loaded: GameRng = ron::from_str(&ron::to_string(&game_rng).unwrap()).unwrap();
+    assert_eq!(
+        rolls(&mut game_rng, RngStream::Combat),
+        rolls(&mut loaded, RngStream::Combat)
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{loaded_app, player_entity, RED};
+use lands_of_mana::{
+    game::{
+        map::Position,
+        player_commands::{GameCommand, PlayerCommandQueue},
+        province::City,
+        recruitment::RecruitmentQueue,
+        save::SaveGame,
+        units::{Unit, UnitType},
+        world::{OfPlayer, StockpileResourceAmount, StockpileResourceType},
+    },
+    headless,
+};
+
+/// Red has a mage tower, which gives 5 death capacity, and no units
+fn city_game() -> SaveGame {
+    let mut game =
+        common::two_player_game(16, 8, vec![common::province(1, "Test", Some(RED))], |_| 0);
+    common::add_city(&mut game, Some(RED), 0, Position::new(4, 4));
+    game
+}
+
+/// Red recruits given number of skeletons, then game runs two ticks
+fn recruit_skeletons(game: SaveGame, count: usize) -> App {
+    let player = game.players[RED].id;
+    let city = game.cities[0].id;
+    let mut app = loaded_app(game);
+    for _ in 0..count {
+        app.world.resource_mut::<PlayerCommandQueue>().push(
+            player,
+            GameCommand::RecruitUnit {
+                city,
+                unit_type: UnitType::Skeleton,
+                rally_point: None,
+            },
+        );
+    }
+    headless::run_ticks(&mut app, 2);
+    app
+}
+
+fn gold(app: &mut App, player: &str) -> f32 {
+    let player = player_entity(app, player);
+    app.world
+        .query::<(&OfPlayer, &StockpileResourceType, &StockpileResourceAmount)>()
+        .iter(&app.world)
+        .find(|(OfPlayer(owner), resource, _)| {
+            *owner == player && **resource == StockpileResourceType::Gold
+        })
+        .map(|(_, _, StockpileResourceAmount(amount))| *amount)
+        .unwrap()
+}
+
+fn queued(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<&RecruitmentQueue, With<City>>()
+        .single(&app.world)
+        .queue
+        .len()
+}
+
+#[test]
+fn recruiting_takes_cost() {
+    let mut recruiting = recruit_skeletons(city_game(), 1);
+    let mut idle = recruit_skeletons(city_game(), 0);
+
+    let cost = UnitType::Skeleton.get_unit_stats().cost[&StockpileResourceType::Gold];
+    assert_eq!(queued(&mut recruiting), 1);
+    assert_eq!(gold(&mut idle, "Red") - gold(&mut recruiting, "Red"), cost);
+}
+
+#[test]
+fn recruiting_without_gold_is_rejected() {
+    let poor_game = || {
+        let mut game = city_game();
+        game.players[RED].stockpiles = vec![(StockpileResourceType::Gold, 10.)];
+        game
+    };
+    let mut recruiting = recruit_skeletons(poor_game(), 1);
+    let mut idle = recruit_skeletons(poor_game(), 0);
+
+    assert_eq!(queued(&mut recruiting), 0);
+    assert_eq!(gold(&mut recruiting, "Red"), gold(&mut idle, "Red"));
+}
+
+#[test]
+fn queued_units_take_capacity() {
+    // Only 5 skeletons fit in the death capacity of the city
+    let mut app = recruit_skeletons(city_game(), 6);
+    assert_eq!(queued(&mut app), 5);
+}
+
+#[test]
+fn recruited_unit_is_child_of_player() {
+    let mut app = recruit_skeletons(city_game(), 1);
+    headless::run_ticks(
+        &mut app,
+        UnitType::Skeleton.get_unit_stats().recruit_ticks as usize,
+    );
+
+    let red = player_entity(&mut app, "Red");
+    let (parent, OfPlayer(owner)) = app
+        .world
+        .query_filtered::<(&Parent, &OfPlayer), With<Unit>>()
+        .single(&app.world);
+    assert_eq!(parent.0, red);
+    assert_eq!(*owner, red);
+}
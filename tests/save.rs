@@ -0,0 +1,86 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{loaded_app, RED};
+use lands_of_mana::{
+    game::{
+        map::Position,
+        save::{SaveGame, SaveGameError},
+    },
+    headless,
+};
+
+fn save_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "lands_of_mana_save_{}_{}.ron",
+        name,
+        std::process::id()
+    ))
+}
+
+fn test_game() -> SaveGame {
+    let mut game = common::two_player_game(
+        8,
+        8,
+        vec![
+            common::province(1, "West", Some(RED)),
+            common::province(2, "East", None),
+        ],
+        |position| (position.x >= 4) as usize,
+    );
+    common::add_city(&mut game, Some(RED), 0, Position::new(1, 1));
+    common::add_skeleton(&mut game, RED, Position::new(2, 2));
+    game
+}
+
+#[test]
+fn save_with_unknown_player_index_is_rejected() {
+    let path = save_path("unknown_player");
+    let mut game = test_game();
+    game.units[0].player = 5;
+    game.write(&path).expect("save should be written");
+    let result = SaveGame::read(&path);
+    std::fs::remove_file(&path).ok();
+
+    assert!(matches!(
+        result,
+        Err(SaveGameError::InvalidIndex {
+            kind: "player",
+            index: 5,
+            count: 2
+        })
+    ));
+}
+
+#[test]
+fn save_with_unknown_province_index_is_rejected() {
+    let mut game = test_game();
+    game.cities[0].province = 2;
+    assert!(matches!(
+        game.validate(),
+        Err(SaveGameError::InvalidIndex {
+            kind: "province",
+            index: 2,
+            count: 2
+        })
+    ));
+}
+
+#[test]
+fn same_game_is_saved_the_same() {
+    let saved = (0..2)
+        .map(|_| {
+            let mut app = loaded_app(test_game());
+            headless::run_ticks(&mut app, 3);
+            let path = save_path("same");
+            SaveGame::from_world(&mut app.world)
+                .and_then(|save| save.write(&path))
+                .expect("game should be saved");
+            let serialized = std::fs::read_to_string(&path);
+            std::fs::remove_file(&path).ok();
+            serialized.expect("save should be readable")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(saved[0], saved[1]);
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use lands_of_mana::game::setup::{GameSetup, GameSetupError, PlayerSetup};
+
+fn player(name: &str, viewer: bool) -> PlayerSetup {
+    PlayerSetup {
+        name: name.to_string(),
+        color: Color::RED,
+        viewer,
+        stockpiles: Vec::new(),
+        units: Vec::new(),
+        cities: Default::default(),
+    }
+}
+
+/// Write setup with given players and read it back
+fn read_with_players(name: &str, players: Vec<PlayerSetup>) -> Result<GameSetup, GameSetupError> {
+    let path: PathBuf = std::env::temp_dir().join(format!(
+        "lands_of_mana_setup_{}_{}.ron",
+        name,
+        std::process::id()
+    ));
+    let setup = GameSetup {
+        players,
+        ..Default::default()
+    };
+    std::fs::write(&path, ron::to_string(&setup).unwrap()).unwrap();
+    let result = GameSetup::read(&path);
+    std::fs::remove_file(&path).ok();
+    result
+}
+
+#[test]
+fn setup_with_players_is_read() {
+    let setup =
+        read_with_players("valid", vec![player("Red", true), player("Blue", false)]).unwrap();
+    assert_eq!(setup.players.len(), 2);
+}
+
+#[test]
+fn setup_without_players_is_rejected() {
+    assert!(matches!(
+        read_with_players("empty", Vec::new()),
+        Err(GameSetupError::NoPlayers { .. })
+    ));
+}
+
+#[test]
+fn setup_with_duplicate_names_is_rejected() {
+    let result = read_with_players(
+        "duplicate",
+        vec![
+            player("Red", true),
+            player("Blue", false),
+            player("Red", false),
+        ],
+    );
+    assert!(matches!(
+        result,
+        Err(GameSetupError::DuplicatePlayer { name, .. }) if name == "Red"
+    ));
+}
+
+#[test]
+fn setup_with_several_viewers_is_rejected() {
+    let result = read_with_players("viewers", vec![player("Red", true), player("Blue", true)]);
+    assert!(matches!(
+        result,
+        Err(GameSetupError::SeveralViewers { names, .. }) if names == ["Red", "Blue"]
+    ));
+}